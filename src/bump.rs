@@ -0,0 +1,257 @@
+use std::sync::Arc;
+
+use crate::{
+    noise::fbm,
+    shapes::Pattern,
+    texture::{ImageTexture, UvMapping},
+    tuple::{vector, Tuple},
+};
+
+/// Offset used for the central differences of a height source.
+const GRADIENT_STEP: f32 = 0.001;
+
+/// Where the detail of a bump map comes from.
+#[derive(Debug, PartialEq, Clone)]
+pub enum BumpSource {
+    /// Fractal noise used as a height field, sampled at the given frequency.
+    Noise { frequency: f32, octaves: usize },
+    /// The brightness of a pattern used as a height field, evaluated through the pattern transformation.
+    Pattern(Arc<Pattern>),
+    /// Tangent space normals read from an image, each channel mapped from 0.0..1.0 to -1.0..1.0.
+    NormalMap(ImageTexture),
+}
+/// Perturbs the normal of a surface before shading to add detail without extra geometry.
+#[derive(Debug, PartialEq, Clone)]
+pub struct BumpMap {
+    pub source: BumpSource,
+    /// How far the normal is tilted, 0.0 leaves it unchanged.
+    pub strength: f32,
+}
+impl BumpMap {
+    /// Create a bump map from noise
+    pub fn new_noise(frequency: f32, strength: f32) -> BumpMap {
+        BumpMap {
+            source: BumpSource::Noise {
+                frequency,
+                octaves: 3,
+            },
+            strength,
+        }
+    }
+    /// Create a bump map that uses the brightness of a pattern as height
+    pub fn new_pattern(pattern: Pattern, strength: f32) -> BumpMap {
+        BumpMap {
+            source: BumpSource::Pattern(Arc::new(pattern)),
+            strength,
+        }
+    }
+    /// Create a bump map from a tangent space normal map
+    pub fn new_normal_map(texture: ImageTexture, strength: f32) -> BumpMap {
+        BumpMap {
+            source: BumpSource::NormalMap(texture),
+            strength,
+        }
+    }
+    /// Perturb a normalized object space normal at an object space point
+    pub fn perturb(&self, object_point: Tuple, normal: Tuple) -> Tuple {
+        match &self.source {
+            BumpSource::Noise { frequency, octaves } => {
+                let gradient = height_gradient(object_point, |p| fbm(p * *frequency, *octaves));
+                tilt(normal, gradient, self.strength)
+            }
+            BumpSource::Pattern(pattern) => {
+                let inverse = pattern.transformation.inverse();
+                let gradient = height_gradient(object_point, |p| {
+                    let c = pattern.pattern_at(inverse * p);
+                    (c.red + c.green + c.blue) / 3.0
+                });
+                tilt(normal, gradient, self.strength)
+            }
+            BumpSource::NormalMap(texture) => {
+                let c = texture.colour_at(object_point);
+                let (tangent, bitangent) = uv_tangent_frame(texture.mapping, object_point, normal);
+                let x = (c.red * 2.0 - 1.0) * self.strength;
+                let y = (c.green * 2.0 - 1.0) * self.strength;
+                let z = c.blue * 2.0 - 1.0;
+                (tangent * x + bitangent * y + normal * z).normalize()
+            }
+        }
+    }
+}
+/// Estimate the gradient of a height function with central differences
+fn height_gradient(point: Tuple, height: impl Fn(Tuple) -> f32) -> Tuple {
+    let dx = vector(GRADIENT_STEP, 0.0, 0.0);
+    let dy = vector(0.0, GRADIENT_STEP, 0.0);
+    let dz = vector(0.0, 0.0, GRADIENT_STEP);
+    vector(
+        height(point + dx) - height(point - dx),
+        height(point + dy) - height(point - dy),
+        height(point + dz) - height(point - dz),
+    ) / (2.0 * GRADIENT_STEP)
+}
+/// Tilt a normal away from the slope of a height field, only the part of the gradient along the surface counts
+fn tilt(normal: Tuple, gradient: Tuple, strength: f32) -> Tuple {
+    let surface_gradient = gradient - normal * gradient.dot(normal);
+    (normal - surface_gradient * strength).normalize()
+}
+/// Build a tangent along increasing u and a bitangent along increasing v of a UV mapping at a point, both
+/// perpendicular to the normal. Falls back to `tangent_frame` where the mapping does not change along the surface.
+pub fn uv_tangent_frame(mapping: UvMapping, point: Tuple, normal: Tuple) -> (Tuple, Tuple) {
+    let du = uv_gradient(point, |p| mapping.map(p).0);
+    let dv = uv_gradient(point, |p| mapping.map(p).1);
+    let tangent = du - normal * du.dot(normal);
+    if tangent.magnitude() < GRADIENT_STEP {
+        return tangent_frame(normal);
+    }
+    let tangent = tangent.normalize();
+    let bitangent = dv - normal * dv.dot(normal) - tangent * dv.dot(tangent);
+    if bitangent.magnitude() < GRADIENT_STEP {
+        return (tangent, normal.cross(tangent));
+    }
+    (tangent, bitangent.normalize())
+}
+/// Estimate the gradient of a texture coordinate with central differences, wrapping across the seams where it
+/// jumps between 1.0 and 0.0
+fn uv_gradient(point: Tuple, coordinate: impl Fn(Tuple) -> f32) -> Tuple {
+    let difference = |offset: Tuple| {
+        let d = coordinate(point + offset) - coordinate(point - offset);
+        d - d.round()
+    };
+    vector(
+        difference(vector(GRADIENT_STEP, 0.0, 0.0)),
+        difference(vector(0.0, GRADIENT_STEP, 0.0)),
+        difference(vector(0.0, 0.0, GRADIENT_STEP)),
+    ) / (2.0 * GRADIENT_STEP)
+}
+/// Build a tangent and bitangent perpendicular to a normal, the tangent lies in the plane made with the y axis
+pub fn tangent_frame(normal: Tuple) -> (Tuple, Tuple) {
+    let up = if normal.y.abs() > 0.999 {
+        vector(0.0, 0.0, 1.0)
+    } else {
+        vector(0.0, 1.0, 0.0)
+    };
+    let tangent = up.cross(normal).normalize();
+    let bitangent = normal.cross(tangent);
+    (tangent, bitangent)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use approx::assert_relative_eq;
+
+    use super::{tangent_frame, BumpMap};
+    use crate::{
+        canvas::Canvas,
+        colour::{Colour, WHITE},
+        ray::{Intersection, Intersections, Ray},
+        shapes::{Object, Pattern},
+        texture::{ImageTexture, TextureFilter, UvMapping},
+        tuple::{point, vector},
+        DEFAULT_EPSILON,
+    };
+
+    #[test]
+    fn flat_pattern_leaves_normal() {
+        let bump = BumpMap::new_pattern(Pattern::new_checkers(WHITE, WHITE), 1.0);
+        let n = bump.perturb(point(0.3, 0.0, 0.6), vector(0.0, 1.0, 0.0));
+        assert_relative_eq!(n, vector(0.0, 1.0, 0.0), epsilon = DEFAULT_EPSILON);
+    }
+    #[test]
+    fn pattern_slope_tilts_normal() {
+        let bump = BumpMap::new_pattern(Pattern::new_test(), 0.5);
+        let n = bump.perturb(point(0.3, 0.0, 0.6), vector(0.0, 1.0, 0.0));
+        let expected = vector(-1.0 / 6.0, 1.0, -1.0 / 6.0).normalize();
+        assert_relative_eq!(n, expected, epsilon = 0.001);
+    }
+    #[test]
+    fn noise_bump_perturbs_normal() {
+        let bump = BumpMap::new_noise(4.0, 0.2);
+        let n = bump.perturb(point(0.37, 0.0, 0.81), vector(0.0, 1.0, 0.0));
+        assert_relative_eq!(n.magnitude(), 1.0, epsilon = DEFAULT_EPSILON);
+        assert!(n.y > 0.0 && n.y < 1.0);
+        let flat =
+            BumpMap::new_noise(4.0, 0.0).perturb(point(0.37, 0.0, 0.81), vector(0.0, 1.0, 0.0));
+        assert_relative_eq!(flat, vector(0.0, 1.0, 0.0), epsilon = DEFAULT_EPSILON);
+    }
+    #[test]
+    fn tangent_frame_is_orthonormal() {
+        for n in [
+            vector(0.0, 1.0, 0.0),
+            vector(1.0, 0.0, 0.0),
+            vector(0.3, -0.5, 0.8).normalize(),
+        ] {
+            let (t, b) = tangent_frame(n);
+            assert_relative_eq!(t.dot(n), 0.0, epsilon = DEFAULT_EPSILON);
+            assert_relative_eq!(b.dot(n), 0.0, epsilon = DEFAULT_EPSILON);
+            assert_relative_eq!(t.dot(b), 0.0, epsilon = DEFAULT_EPSILON);
+            assert_relative_eq!(t.magnitude(), 1.0, epsilon = DEFAULT_EPSILON);
+        }
+    }
+    #[test]
+    fn normal_map_rotates_into_tangent_space() {
+        let flat = Arc::new(Canvas::new(2, 2, Colour::new(0.5, 0.5, 1.0)));
+        let texture = ImageTexture::new(flat, UvMapping::Planar, TextureFilter::Nearest);
+        let n = BumpMap::new_normal_map(texture, 1.0)
+            .perturb(point(0.2, 0.0, 0.2), vector(0.0, 0.0, -1.0));
+        assert_relative_eq!(n, vector(0.0, 0.0, -1.0), epsilon = DEFAULT_EPSILON);
+        // Red tilts towards increasing u, which the planar mapping lays along x
+        let tilted = Arc::new(Canvas::new(2, 2, Colour::new(1.0, 0.5, 0.5)));
+        let texture = ImageTexture::new(tilted, UvMapping::Planar, TextureFilter::Nearest);
+        let n = BumpMap::new_normal_map(texture, 1.0)
+            .perturb(point(0.2, 0.0, 0.2), vector(0.0, 0.0, -1.0));
+        assert_relative_eq!(n, vector(1.0, 0.0, 0.0), epsilon = DEFAULT_EPSILON);
+    }
+    #[test]
+    fn normal_map_follows_texture_axes() {
+        let map = |c: Colour, mapping: UvMapping, p, n| {
+            let texture = ImageTexture::new(
+                Arc::new(Canvas::new(2, 2, c)),
+                mapping,
+                TextureFilter::Nearest,
+            );
+            BumpMap::new_normal_map(texture, 1.0).perturb(p, n)
+        };
+        let plus_u = Colour::new(0.75, 0.5, 1.0);
+        let plus_v = Colour::new(0.5, 0.75, 1.0);
+        let up = vector(0.0, 1.0, 0.0);
+        // On a plane u runs along x and v along z
+        let n = map(plus_u, UvMapping::Planar, point(0.3, 0.0, 0.6), up);
+        assert_relative_eq!(n, vector(0.5, 1.0, 0.0).normalize(), epsilon = 0.001);
+        let n = map(plus_v, UvMapping::Planar, point(0.3, 0.0, 0.6), up);
+        assert_relative_eq!(n, vector(0.0, 1.0, 0.5).normalize(), epsilon = 0.001);
+        // On the front of a sphere u runs towards +x and v upwards
+        let front = vector(0.0, 0.0, -1.0);
+        let n = map(plus_u, UvMapping::Spherical, point(0.0, 0.0, -1.0), front);
+        assert_relative_eq!(n, vector(0.5, 0.0, -1.0).normalize(), epsilon = 0.001);
+        let n = map(plus_v, UvMapping::Spherical, point(0.0, 0.0, -1.0), front);
+        assert_relative_eq!(n, vector(0.0, 0.5, -1.0).normalize(), epsilon = 0.001);
+        // The seam where u wraps from 1.0 to 0.0 does not flip the tangent
+        let n = map(plus_u, UvMapping::Planar, point(1.0, 0.0, 0.6), up);
+        assert_relative_eq!(n, vector(0.5, 1.0, 0.0).normalize(), epsilon = 0.001);
+    }
+    #[test]
+    fn shading_normal_uses_bump_map() {
+        let mut s = Object::new_plane();
+        s.material.bump = Some(BumpMap::new_pattern(Pattern::new_test(), 0.5));
+        let p = point(0.3, 0.0, 0.6);
+        assert_eq!(s.normal_at(p), vector(0.0, 1.0, 0.0));
+        assert_relative_eq!(
+            s.shading_normal_at(p),
+            vector(-1.0 / 6.0, 1.0, -1.0 / 6.0).normalize(),
+            epsilon = 0.001
+        );
+    }
+    #[test]
+    fn computations_keep_geometric_offsets() {
+        let mut s = Object::new_plane();
+        s.material.bump = Some(BumpMap::new_pattern(Pattern::new_test(), 0.5));
+        let r = Ray::new(point(0.3, 1.0, 0.6), vector(0.0, -1.0, 0.0));
+        let i = Intersection::new(1.0, s);
+        let comps = r.prepare_computations(&i, Intersections::new(vec![i.clone()]));
+        assert_relative_eq!(comps.over_point, point(0.3, DEFAULT_EPSILON, 0.6));
+        assert!(comps.normalv.x < 0.0 && comps.normalv.z < 0.0);
+        assert!(!comps.inside);
+    }
+}
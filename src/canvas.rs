@@ -1,6 +1,7 @@
 use crate::colour::Colour;
 
-/// A struct representing a canvas. It can create a string containing a representation of itself in ppm format. 
+/// A struct representing a canvas. It can create a string containing a representation of itself in ppm format.
+#[derive(Debug, Clone, PartialEq)]
 pub struct Canvas {
     width: usize,
     height: usize,
@@ -63,6 +64,140 @@ impl Canvas {
         }
         str
     }
+    /// Create a canvas from a string containing an image in plain (P3) ppm format.
+    pub fn from_ppm(ppm: &str) -> Result<Canvas, String> {
+        let mut tokens = ppm
+            .lines()
+            .map(|line| line.split('#').next().unwrap_or(""))
+            .flat_map(|line| line.split_whitespace());
+        if tokens.next() != Some("P3") {
+            return Err("Only plain ppm (P3) images are supported".to_string());
+        }
+        let mut header = [0usize; 3];
+        for value in header.iter_mut() {
+            *value = tokens
+                .next()
+                .and_then(|t| t.parse().ok())
+                .ok_or("Invalid ppm header")?;
+        }
+        let [width, height, max] = header;
+        if max == 0 {
+            return Err("Invalid ppm maximum colour value".to_string());
+        }
+        let mut canvas = Canvas::new(width, height, Colour::new(0.0, 0.0, 0.0));
+        for pixel in canvas.pixels.iter_mut() {
+            let mut channels = [0.0; 3];
+            for channel in channels.iter_mut() {
+                let value: usize = tokens
+                    .next()
+                    .and_then(|t| t.parse().ok())
+                    .ok_or("Missing or invalid ppm pixel data")?;
+                *channel = value as f32 / max as f32;
+            }
+            *pixel = Colour::new(channels[0], channels[1], channels[2]);
+        }
+        Ok(canvas)
+    }
+    /// Create a canvas from the bytes of a Radiance HDR (RGBE) image, flat or run length encoded scanlines.
+    /// Colours are not clamped, so values above 1.0 are kept.
+    pub fn from_hdr(hdr: &[u8]) -> Result<Canvas, String> {
+        let mut lines = HdrReader {
+            data: hdr,
+            position: 0,
+        };
+        let magic = lines.line().ok_or("Missing hdr header")?;
+        if magic != "#?RADIANCE" && magic != "#?RGBE" {
+            return Err("Not a Radiance hdr image".to_string());
+        }
+        loop {
+            let line = lines.line().ok_or("Unterminated hdr header")?;
+            if line.is_empty() {
+                break;
+            }
+            if let Some(format) = line.strip_prefix("FORMAT=") {
+                if format != "32-bit_rle_rgbe" {
+                    return Err(format!("Unsupported hdr format {}", format));
+                }
+            }
+        }
+        let resolution = lines.line().ok_or("Missing hdr resolution")?;
+        let parts: Vec<&str> = resolution.split_whitespace().collect();
+        let (height, width) = match parts.as_slice() {
+            ["-Y", h, "+X", w] => (
+                h.parse::<usize>().map_err(|_| "Invalid hdr height")?,
+                w.parse::<usize>().map_err(|_| "Invalid hdr width")?,
+            ),
+            _ => return Err("Only -Y h +X w hdr orientation is supported".to_string()),
+        };
+        let mut data = &hdr[lines.position..];
+        let mut canvas = Canvas::new(width, height, Colour::new(0.0, 0.0, 0.0));
+        let mut scanline = vec![[0u8; 4]; width];
+        for row in 0..height {
+            let rle = (8..32768).contains(&width)
+                && data.len() >= 4
+                && data[0] == 2
+                && data[1] == 2
+                && ((data[2] as usize) << 8 | data[3] as usize) == width;
+            if rle {
+                data = &data[4..];
+                for channel in 0..4 {
+                    let mut x = 0;
+                    while x < width {
+                        let (&count, rest) = data.split_first().ok_or("Truncated hdr data")?;
+                        data = rest;
+                        if count > 128 {
+                            let count = count as usize - 128;
+                            let (&value, rest) = data.split_first().ok_or("Truncated hdr data")?;
+                            data = rest;
+                            if x + count > width {
+                                return Err("Invalid hdr run length".to_string());
+                            }
+                            for pixel in &mut scanline[x..x + count] {
+                                pixel[channel] = value;
+                            }
+                            x += count;
+                        } else {
+                            let count = count as usize;
+                            if count == 0 || x + count > width || data.len() < count {
+                                return Err("Invalid hdr run length".to_string());
+                            }
+                            for (pixel, value) in
+                                scanline[x..x + count].iter_mut().zip(&data[..count])
+                            {
+                                pixel[channel] = *value;
+                            }
+                            data = &data[count..];
+                            x += count;
+                        }
+                    }
+                }
+            } else {
+                if data.len() < width * 4 {
+                    return Err("Truncated hdr data".to_string());
+                }
+                for (pixel, rgbe) in scanline.iter_mut().zip(data.chunks_exact(4)) {
+                    pixel.copy_from_slice(rgbe);
+                }
+                data = &data[width * 4..];
+            }
+            for (x, rgbe) in scanline.iter().enumerate() {
+                canvas.write_pixel(x, row, rgbe_to_colour(*rgbe));
+            }
+        }
+        Ok(canvas)
+    }
+    /// Convert the canvas to a Radiance HDR (RGBE) image with flat scanlines
+    pub fn to_hdr(&self) -> Vec<u8> {
+        let mut hdr = format!(
+            "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n",
+            self.height, self.width
+        )
+        .into_bytes();
+        for colour in &self.pixels {
+            hdr.extend_from_slice(&colour_to_rgbe(*colour));
+        }
+        hdr
+    }
     /// Return the height of the canvas.
     pub fn get_height(&self) -> usize {
         self.height
@@ -72,6 +207,48 @@ impl Canvas {
         self.width
     }
 }
+/// Reads the newline terminated header lines of an hdr image
+struct HdrReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+impl<'a> HdrReader<'a> {
+    fn line(&mut self) -> Option<&'a str> {
+        let rest = &self.data[self.position..];
+        let end = rest.iter().position(|b| *b == b'\n')?;
+        self.position += end + 1;
+        std::str::from_utf8(&rest[..end])
+            .ok()
+            .map(|line| line.trim_end_matches('\r'))
+    }
+}
+/// Decode a pixel with a shared exponent, the mantissas are scaled by 2^(exponent - 128) / 256
+fn rgbe_to_colour(rgbe: [u8; 4]) -> Colour {
+    if rgbe[3] == 0 {
+        return Colour::new(0.0, 0.0, 0.0);
+    }
+    let scale = 2f32.powi(rgbe[3] as i32 - 136);
+    Colour::new(
+        rgbe[0] as f32 * scale,
+        rgbe[1] as f32 * scale,
+        rgbe[2] as f32 * scale,
+    )
+}
+fn colour_to_rgbe(colour: Colour) -> [u8; 4] {
+    let largest = colour.red.max(colour.green).max(colour.blue);
+    if largest < 1e-32 {
+        return [0, 0, 0, 0];
+    }
+    let exponent = largest.log2().floor() as i32 + 1;
+    let scale = 256.0 / 2f32.powi(exponent);
+    let mantissa = |c: f32| (c.max(0.0) * scale).min(255.0) as u8;
+    [
+        mantissa(colour.red),
+        mantissa(colour.green),
+        mantissa(colour.blue),
+        (exponent + 128) as u8,
+    ]
+}
 
 #[cfg(test)]
 mod tests {
@@ -199,10 +376,64 @@ mod tests {
         assert_eq!(line, "153 255 204 153 255 204 153 255 204 153 255 204 153");
     }
     #[test]
+    fn read_ppm() {
+        let ppm = "P3\n# a comment\n2 1\n255\n255 0 0 0 51 255\n";
+        let a = canvas::Canvas::from_ppm(ppm).unwrap();
+        assert_eq!(a.get_width(), 2);
+        assert_eq!(a.get_height(), 1);
+        assert_eq!(a.pixel_at(0, 0), colour::RED);
+        assert_eq!(a.pixel_at(1, 0), colour::Colour::new(0.0, 0.2, 1.0));
+    }
+    #[test]
+    fn ppm_round_trip() {
+        let mut a = canvas::Canvas::new(10, 2, colour::Colour::new(1.0, 0.8, 0.6));
+        a.write_pixel(3, 1, colour::BLUE);
+        let b = canvas::Canvas::from_ppm(&a.to_ppm()).unwrap();
+        assert_eq!(a, b);
+    }
+    #[test]
+    fn read_ppm_errors() {
+        assert!(canvas::Canvas::from_ppm("P6\n1 1\n255\n").is_err());
+        assert!(canvas::Canvas::from_ppm("P3\n2 1\n255\n255 0 0\n").is_err());
+    }
+    #[test]
     fn newline_at_end() {
         let a = canvas::Canvas::new(5, 5, colour::RED);
         let str = a.to_ppm();
         let last = str.chars().last().unwrap();
         assert_eq!(last, '\n');
     }
+    #[test]
+    fn hdr_round_trip() {
+        let mut a = canvas::Canvas::new(3, 2, colour::BLACK);
+        a.write_pixel(0, 0, colour::Colour::new(1.0, 0.5, 0.25));
+        a.write_pixel(2, 1, colour::Colour::new(12.0, 3.0, 0.0));
+        let b = canvas::Canvas::from_hdr(&a.to_hdr()).unwrap();
+        assert_eq!(b.get_width(), 3);
+        assert_eq!(b.get_height(), 2);
+        for x in 0..3 {
+            for y in 0..2 {
+                approx::assert_relative_eq!(b.pixel_at(x, y), a.pixel_at(x, y), epsilon = 0.05);
+            }
+        }
+    }
+    #[test]
+    fn read_run_length_encoded_hdr() {
+        let mut hdr = b"#?RADIANCE\n# made by hand\nFORMAT=32-bit_rle_rgbe\n\n-Y 1 +X 8\n".to_vec();
+        hdr.extend_from_slice(&[2, 2, 0, 8]);
+        // Red: a run of eight 128s, green: eight literal values, blue: a run of zeros, exponent: a run of 129
+        hdr.extend_from_slice(&[136, 128]);
+        hdr.extend_from_slice(&[8, 0, 32, 64, 96, 128, 160, 192, 224]);
+        hdr.extend_from_slice(&[136, 0]);
+        hdr.extend_from_slice(&[136, 129]);
+        let c = canvas::Canvas::from_hdr(&hdr).unwrap();
+        assert_eq!(c.pixel_at(0, 0), colour::Colour::new(1.0, 0.0, 0.0));
+        assert_eq!(c.pixel_at(7, 0), colour::Colour::new(1.0, 1.75, 0.0));
+    }
+    #[test]
+    fn read_hdr_errors() {
+        assert!(canvas::Canvas::from_hdr(b"P3\n1 1\n255\n").is_err());
+        assert!(canvas::Canvas::from_hdr(b"#?RADIANCE\n\n+Y 1 +X 1\n\x80\x80\x80\x81").is_err());
+        assert!(canvas::Canvas::from_hdr(b"#?RADIANCE\n\n-Y 1 +X 2\n\x80\x80\x80\x81").is_err());
+    }
 }
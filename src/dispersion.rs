@@ -0,0 +1,110 @@
+use crate::colour::{Colour, BLACK};
+
+/// Wavelength in micrometres used for the red channel.
+pub const RED_WAVELENGTH: f32 = 0.650;
+/// Wavelength in micrometres used for the green channel.
+pub const GREEN_WAVELENGTH: f32 = 0.550;
+/// Wavelength in micrometres used for the blue channel.
+pub const BLUE_WAVELENGTH: f32 = 0.450;
+/// Wavelengths in micrometres of the Fraunhofer d, F and C lines used to define the Abbe number.
+const D_LINE: f32 = 0.5876;
+const F_LINE: f32 = 0.4861;
+const C_LINE: f32 = 0.6563;
+
+/// A refractive index that changes with wavelength, following Cauchy's equation n = a + b / wavelength².
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Dispersion {
+    pub a: f32,
+    /// In square micrometres.
+    pub b: f32,
+}
+impl Dispersion {
+    /// Create a dispersion from Cauchy coefficients, b is in square micrometres
+    pub fn cauchy(a: f32, b: f32) -> Dispersion {
+        Dispersion { a, b }
+    }
+    /// Create a dispersion from the refractive index at the sodium d line and the Abbe number, lower Abbe numbers disperse more
+    pub fn from_abbe(index: f32, abbe: f32) -> Dispersion {
+        let b = (index - 1.0) / (abbe * (1.0 / F_LINE.powi(2) - 1.0 / C_LINE.powi(2)));
+        let a = index - b / D_LINE.powi(2);
+        Dispersion { a, b }
+    }
+    /// The refractive index at a wavelength in micrometres
+    pub fn index_at(&self, wavelength: f32) -> f32 {
+        self.a + self.b / wavelength.powi(2)
+    }
+}
+/// A colour channel, traced on its own at its wavelength once a dispersive surface has split the light.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Channel {
+    Red,
+    Green,
+    Blue,
+}
+impl Channel {
+    pub const ALL: [Channel; 3] = [Channel::Red, Channel::Green, Channel::Blue];
+    /// The wavelength in micrometres used for the channel
+    pub fn wavelength(&self) -> f32 {
+        match self {
+            Channel::Red => RED_WAVELENGTH,
+            Channel::Green => GREEN_WAVELENGTH,
+            Channel::Blue => BLUE_WAVELENGTH,
+        }
+    }
+    /// Keep only this channel of a colour
+    pub fn pick(&self, colour: Colour) -> Colour {
+        match self {
+            Channel::Red => Colour::new(colour.red, 0.0, 0.0),
+            Channel::Green => Colour::new(0.0, colour.green, 0.0),
+            Channel::Blue => Colour::new(0.0, 0.0, colour.blue),
+        }
+    }
+}
+/// Put a colour back together from the colour traced for each channel
+pub fn combine_channels(mut trace: impl FnMut(Channel) -> Colour) -> Colour {
+    Channel::ALL.iter().fold(BLACK, |total, channel| {
+        total + channel.pick(trace(*channel))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+
+    use super::{
+        combine_channels, Channel, Dispersion, BLUE_WAVELENGTH, C_LINE, D_LINE, F_LINE,
+        GREEN_WAVELENGTH, RED_WAVELENGTH,
+    };
+    use crate::colour::{Colour, BLUE, GREEN, RED};
+
+    #[test]
+    fn cauchy_index() {
+        let d = Dispersion::cauchy(1.5, 0.01);
+        assert_relative_eq!(d.index_at(0.5), 1.54);
+    }
+    #[test]
+    fn abbe_number_round_trips() {
+        // Schott BK7 crown glass
+        let d = Dispersion::from_abbe(1.5168, 64.17);
+        assert_relative_eq!(d.index_at(D_LINE), 1.5168, epsilon = 0.00001);
+        let abbe = (d.index_at(D_LINE) - 1.0) / (d.index_at(F_LINE) - d.index_at(C_LINE));
+        assert_relative_eq!(abbe, 64.17, epsilon = 0.01);
+    }
+    #[test]
+    fn blue_bends_more_than_red() {
+        let d = Dispersion::from_abbe(2.417, 55.3);
+        assert!(d.index_at(BLUE_WAVELENGTH) > d.index_at(GREEN_WAVELENGTH));
+        assert!(d.index_at(GREEN_WAVELENGTH) > d.index_at(RED_WAVELENGTH));
+    }
+    #[test]
+    fn channels_combine_into_colour() {
+        let traced = combine_channels(|channel| match channel {
+            Channel::Red => RED,
+            Channel::Green => Colour::new(0.5, 0.25, 0.5),
+            Channel::Blue => GREEN,
+        });
+        assert_eq!(traced, Colour::new(1.0, 0.25, 0.0));
+        assert_eq!(Channel::Blue.pick(Colour::new(0.2, 0.4, 0.6)), BLUE * 0.6);
+        assert_eq!(Channel::Red.wavelength(), RED_WAVELENGTH);
+    }
+}
@@ -0,0 +1,255 @@
+use std::{f32::consts::PI, sync::Arc};
+
+use crate::{
+    canvas::Canvas,
+    colour::{Colour, BLACK},
+    sampling::random,
+    sky::PhysicalSky,
+    texture::{cube_map, sample, spherical_map, TextureFilter},
+    tuple::{point, vector, Tuple},
+};
+
+/// What a ray sees when it leaves the world without hitting anything.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Background {
+    Solid(Colour),
+    /// Blend from bottom looking straight down to top looking straight up.
+    Gradient {
+        bottom: Colour,
+        top: Colour,
+    },
+    /// An image covering every direction, u follows the longitude and v the latitude as in `UvMapping::Spherical`.
+    Equirectangular(Arc<Canvas>),
+    /// Six square faces laid out as a horizontal cross, as in `UvMapping::Cube`.
+    CubeMap(Arc<Canvas>),
+    /// Daylight from a physical sky model, add `PhysicalSky::sun_light` to the lights to match it.
+    Sky(PhysicalSky),
+}
+impl Default for Background {
+    fn default() -> Self {
+        Background::Solid(BLACK)
+    }
+}
+impl Background {
+    /// The colour seen looking along direction
+    pub fn colour_at(&self, direction: Tuple) -> Colour {
+        let d = direction.normalize();
+        match self {
+            Background::Solid(colour) => *colour,
+            Background::Gradient { bottom, top } => {
+                let fraction = (d.y + 1.0) / 2.0;
+                *bottom + (*top - *bottom) * fraction
+            }
+            Background::Equirectangular(image) => {
+                let (u, v) = spherical_map(point(d.x, d.y, d.z));
+                sample(image, u, v, TextureFilter::Bilinear)
+            }
+            Background::CubeMap(image) => {
+                // Push the direction out onto the faces of the cube from -1 to 1
+                let largest = d.x.abs().max(d.y.abs()).max(d.z.abs());
+                let (u, v) = cube_map(point(d.x / largest, d.y / largest, d.z / largest));
+                sample(image, u, v, TextureFilter::Bilinear)
+            }
+            Background::Sky(sky) => sky.colour_at(d),
+        }
+    }
+}
+/// Light arriving from every direction, read from an equirectangular (usually HDR) image laid out as in
+/// `Background::Equirectangular`. Directions are importance sampled by the luminance of their pixel.
+#[derive(Debug, PartialEq, Clone)]
+pub struct EnvironmentLight {
+    image: Arc<Canvas>,
+    /// Running total of the probability of choosing each pixel, row by row from the top.
+    cdf: Arc<Vec<f32>>,
+    /// Directions sampled per hit.
+    pub samples: usize,
+}
+impl EnvironmentLight {
+    /// Create an environment light from an equirectangular image and the number of directions sampled per hit.
+    /// Fails for an empty image, which has no directions to sample.
+    pub fn new(image: Arc<Canvas>, samples: usize) -> Result<EnvironmentLight, String> {
+        let (width, height) = (image.get_width(), image.get_height());
+        if width == 0 || height == 0 {
+            return Err("Environment light image is empty".to_string());
+        }
+        let mut cdf = Vec::with_capacity(width * height);
+        let mut total = 0.0;
+        for y in 0..height {
+            // Rows near the poles cover less of the sphere
+            let sin_theta = (PI * (y as f32 + 0.5) / height as f32).sin();
+            for x in 0..width {
+                // A small floor keeps black pixels reachable so the estimate stays unbiased
+                total += (luminance(image.pixel_at(x, y)) + 1e-6) * sin_theta;
+                cdf.push(total);
+            }
+        }
+        for value in cdf.iter_mut() {
+            *value /= total;
+        }
+        Ok(EnvironmentLight {
+            image,
+            cdf: Arc::new(cdf),
+            samples: samples.max(1),
+        })
+    }
+    /// Pick a random direction, returning it with the light arriving from it and the probability density of picking it
+    pub fn sample(&self) -> (Tuple, Colour, f32) {
+        let width = self.image.get_width();
+        let target = random();
+        let index = self
+            .cdf
+            .partition_point(|value| *value < target)
+            .min(self.cdf.len() - 1);
+        let (x, y) = (index % width, index / width);
+        let u = (x as f32 + random()) / width as f32;
+        let v = 1.0 - (y as f32 + random()) / self.image.get_height() as f32;
+        let direction = uv_direction(u, v);
+        (direction, self.image.pixel_at(x, y), self.pdf(direction))
+    }
+    /// The probability density over solid angle of sample returning direction
+    pub fn pdf(&self, direction: Tuple) -> f32 {
+        let (width, height) = (self.image.get_width(), self.image.get_height());
+        let (x, y, sin_theta) = self.pixel(direction);
+        let index = y * width + x;
+        let previous = if index == 0 { 0.0 } else { self.cdf[index - 1] };
+        if sin_theta <= 0.0 {
+            return 0.0;
+        }
+        // A pixel covers 2pi^2 sin(theta) / (width height) of solid angle
+        (self.cdf[index] - previous) * (width * height) as f32 / (2.0 * PI * PI * sin_theta)
+    }
+    /// The light arriving from direction
+    pub fn radiance(&self, direction: Tuple) -> Colour {
+        let (x, y, _) = self.pixel(direction);
+        self.image.pixel_at(x, y)
+    }
+    /// The pixel seen along direction and the sine of its angle from straight up
+    fn pixel(&self, direction: Tuple) -> (usize, usize, f32) {
+        let (width, height) = (self.image.get_width(), self.image.get_height());
+        let d = direction.normalize();
+        let (u, v) = spherical_map(point(d.x, d.y, d.z));
+        let x = ((u * width as f32) as usize).min(width - 1);
+        let y = (((1.0 - v) * height as f32) as usize).min(height - 1);
+        (x, y, (1.0 - d.y * d.y).max(0.0).sqrt())
+    }
+}
+/// Relative brightness of a colour as seen by the eye
+pub fn luminance(colour: Colour) -> f32 {
+    0.2126 * colour.red + 0.7152 * colour.green + 0.0722 * colour.blue
+}
+/// The direction that `spherical_map` turns into (u, v)
+fn uv_direction(u: f32, v: f32) -> Tuple {
+    let theta = 2.0 * PI * (0.5 - u);
+    let phi = PI * (1.0 - v);
+    vector(phi.sin() * theta.sin(), phi.cos(), phi.sin() * theta.cos())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use approx::assert_relative_eq;
+
+    use super::{uv_direction, Background, EnvironmentLight};
+    use crate::{
+        canvas::Canvas,
+        colour::{Colour, BLACK, BLUE, RED, WHITE},
+        texture::spherical_map,
+        tuple::{point, vector},
+        DEFAULT_EPSILON,
+    };
+
+    #[test]
+    fn solid_background() {
+        let b = Background::Solid(RED);
+        assert_eq!(b.colour_at(vector(0.3, -0.2, 1.0)), RED);
+        assert_eq!(
+            Background::default().colour_at(vector(0.0, 1.0, 0.0)),
+            BLACK
+        );
+    }
+    #[test]
+    fn gradient_background() {
+        let b = Background::Gradient {
+            bottom: WHITE,
+            top: BLUE,
+        };
+        assert_eq!(b.colour_at(vector(0.0, 2.0, 0.0)), BLUE);
+        assert_eq!(b.colour_at(vector(0.0, -1.0, 0.0)), WHITE);
+        assert_relative_eq!(
+            b.colour_at(vector(1.0, 0.0, 0.0)),
+            Colour::new(0.5, 0.5, 1.0),
+            epsilon = DEFAULT_EPSILON
+        );
+    }
+    #[test]
+    fn equirectangular_background() {
+        let mut image = Canvas::new(4, 2, BLACK);
+        // The top row is the upper half of the sky
+        for x in 0..4 {
+            image.write_pixel(x, 0, WHITE);
+        }
+        let b = Background::Equirectangular(Arc::new(image));
+        assert_eq!(b.colour_at(vector(0.0, 1.0, 0.0)), WHITE);
+        assert_eq!(b.colour_at(vector(0.0, -1.0, 0.0)), BLACK);
+    }
+    #[test]
+    fn cube_map_background() {
+        let mut image = Canvas::new(8, 6, BLACK);
+        // The up face sits above the front face of the cross
+        for x in 2..4 {
+            for y in 0..2 {
+                image.write_pixel(x, y, RED);
+            }
+        }
+        let b = Background::CubeMap(Arc::new(image));
+        assert_eq!(b.colour_at(vector(0.0, 5.0, 0.0)), RED);
+        assert_eq!(b.colour_at(vector(0.0, 0.0, 1.0)), BLACK);
+    }
+    #[test]
+    fn uv_direction_inverts_spherical_map() {
+        for (u, v) in [(0.1, 0.2), (0.5, 0.5), (0.8, 0.9), (0.3, 0.6)] {
+            let d = uv_direction(u, v);
+            let (mu, mv) = spherical_map(point(d.x, d.y, d.z));
+            assert_relative_eq!(mu, u, epsilon = DEFAULT_EPSILON * 10.0);
+            assert_relative_eq!(mv, v, epsilon = DEFAULT_EPSILON * 10.0);
+        }
+    }
+    #[test]
+    fn environment_samples_bright_pixels() {
+        let mut image = Canvas::new(8, 4, BLACK);
+        image.write_pixel(2, 1, Colour::new(100.0, 100.0, 100.0));
+        let light = EnvironmentLight::new(Arc::new(image), 1).unwrap();
+        let mut bright = 0;
+        for _ in 0..200 {
+            let (direction, radiance, pdf) = light.sample();
+            assert_relative_eq!(direction.magnitude(), 1.0, epsilon = DEFAULT_EPSILON * 10.0);
+            assert_eq!(light.radiance(direction), radiance);
+            assert!(pdf > 0.0);
+            if radiance.red > 0.0 {
+                bright += 1;
+            }
+        }
+        assert!(bright > 190);
+    }
+    #[test]
+    fn environment_pdf_integrates_to_one() {
+        let mut image = Canvas::new(16, 8, WHITE);
+        image.write_pixel(3, 2, Colour::new(20.0, 5.0, 1.0));
+        image.write_pixel(10, 6, Colour::new(0.0, 8.0, 0.0));
+        let light = EnvironmentLight::new(Arc::new(image), 1).unwrap();
+        // Average pdf / uniform pdf over uniformly chosen directions
+        let mut total = 0.0;
+        let n = 20000;
+        for _ in 0..n {
+            let d = crate::sampling::uniform_sphere();
+            total += light.pdf(d) * 4.0 * std::f32::consts::PI;
+        }
+        assert_relative_eq!(total / n as f32, 1.0, epsilon = 0.05);
+    }
+    #[test]
+    fn environment_light_needs_pixels() {
+        assert!(EnvironmentLight::new(Arc::new(Canvas::new(0, 4, WHITE)), 1).is_err());
+        assert!(EnvironmentLight::new(Arc::new(Canvas::new(8, 0, WHITE)), 1).is_err());
+    }
+}
@@ -0,0 +1,284 @@
+use crate::{
+    canvas::Canvas,
+    environment::luminance,
+    tuple::{point, vector, Tuple},
+    DEFAULT_EPSILON,
+};
+
+/// A terrain surface over the unit square of the xz-plane, with the height of each grid point given as y.
+/// Each grid cell is split into two triangles along its diagonal. Rays walk the cells they cross in order,
+/// so only a handful of triangles are tested however fine the grid is.
+#[derive(Debug, Clone)]
+pub struct Heightfield {
+    /// Grid points along x.
+    width: usize,
+    /// Grid points along z.
+    depth: usize,
+    /// Heights stored row by row, one row of width points for each step along z.
+    heights: Vec<f32>,
+    /// Vertex normals from the slope of the neighbouring heights, interpolated across each triangle.
+    normals: Vec<Tuple>,
+    min_height: f32,
+    max_height: f32,
+}
+impl Heightfield {
+    /// Create a heightfield from width * depth heights stored row by row.
+    /// Fails unless there are at least two points along each axis and exactly width * depth heights.
+    pub fn new(width: usize, depth: usize, heights: Vec<f32>) -> Result<Heightfield, String> {
+        if width < 2 || depth < 2 {
+            return Err("Heightfield needs at least two points along each axis".to_string());
+        }
+        if heights.len() != width * depth {
+            return Err("Heightfield needs width * depth heights".to_string());
+        }
+        let min_height = heights.iter().cloned().fold(f32::INFINITY, f32::min);
+        let max_height = heights.iter().cloned().fold(f32::NEG_INFINITY, f32::max);
+        let mut field = Heightfield {
+            width,
+            depth,
+            heights,
+            normals: Vec::new(),
+            min_height,
+            max_height,
+        };
+        field.normals = (0..depth)
+            .flat_map(|j| (0..width).map(move |i| (i, j)))
+            .map(|(i, j)| field.vertex_normal(i, j))
+            .collect();
+        Ok(field)
+    }
+    /// Create a heightfield from the brightness of each pixel of a grayscale image, rows of the image run along z
+    pub fn from_canvas(image: &Canvas) -> Result<Heightfield, String> {
+        let (width, depth) = (image.get_width(), image.get_height());
+        let heights = (0..depth)
+            .flat_map(|j| (0..width).map(move |i| (i, j)))
+            .map(|(i, j)| luminance(image.pixel_at(i, j)))
+            .collect();
+        Heightfield::new(width, depth, heights)
+    }
+    /// Create a heightfield by sampling a function of x and z, each running from 0.0 to 1.0, such as a noise function
+    pub fn from_fn(
+        width: usize,
+        depth: usize,
+        height: impl Fn(f32, f32) -> f32,
+    ) -> Result<Heightfield, String> {
+        let heights = (0..depth)
+            .flat_map(|j| (0..width).map(move |i| (i, j)))
+            .map(|(i, j)| height(i as f32 / (width - 1) as f32, j as f32 / (depth - 1) as f32))
+            .collect();
+        Heightfield::new(width, depth, heights)
+    }
+    /// The height of the grid point in column i and row j
+    pub fn height(&self, i: usize, j: usize) -> f32 {
+        self.heights[j * self.width + i]
+    }
+    /// Find where a ray in object space crosses the surface, walking the grid cells under the ray in order
+    pub fn intersect(&self, origin: Tuple, direction: Tuple) -> Vec<f32> {
+        // Work in grid units, where each cell is one unit square. Scaling x and z leaves t unchanged.
+        let (sx, sz) = ((self.width - 1) as f32, (self.depth - 1) as f32);
+        let origin = point(origin.x * sx, origin.y, origin.z * sz);
+        let direction = vector(direction.x * sx, direction.y, direction.z * sz);
+        let (x0, x1) = slab(origin.x, direction.x, 0.0, sx);
+        let (y0, y1) = slab(origin.y, direction.y, self.min_height, self.max_height);
+        let (z0, z1) = slab(origin.z, direction.z, 0.0, sz);
+        let (t_start, t_end) = (x0.max(y0).max(z0), x1.min(y1).min(z1));
+        if t_start > t_end {
+            return vec![];
+        }
+        let start = origin + direction * t_start;
+        let mut i = (start.x.floor() as isize).clamp(0, self.width as isize - 2);
+        let mut j = (start.z.floor() as isize).clamp(0, self.depth as isize - 2);
+        let (step_i, mut next_x, delta_x) = dda_axis(origin.x, direction.x, i);
+        let (step_j, mut next_z, delta_z) = dda_axis(origin.z, direction.z, j);
+        let mut t_enter = t_start;
+        let mut hits = Vec::new();
+        while i >= 0 && j >= 0 && i < self.width as isize - 1 && j < self.depth as isize - 1 {
+            let t_exit = next_x.min(next_z).min(t_end);
+            for t in self.intersect_cell(origin, direction, i as usize, j as usize) {
+                // A crossing on the edge between two cells is found from both of them
+                let repeated = hits
+                    .last()
+                    .is_some_and(|last: &f32| (t - last).abs() < DEFAULT_EPSILON);
+                if t >= t_enter - DEFAULT_EPSILON && t <= t_exit + DEFAULT_EPSILON && !repeated {
+                    hits.push(t);
+                }
+            }
+            if t_exit >= t_end {
+                break;
+            }
+            if next_x < next_z {
+                i += step_i;
+                next_x += delta_x;
+            } else {
+                j += step_j;
+                next_z += delta_z;
+            }
+            t_enter = t_exit;
+        }
+        hits
+    }
+    /// The interpolated normal at a point in object space on the surface
+    pub fn normal(&self, p: Tuple) -> Tuple {
+        let gx = (p.x * (self.width - 1) as f32).clamp(0.0, (self.width - 1) as f32);
+        let gz = (p.z * (self.depth - 1) as f32).clamp(0.0, (self.depth - 1) as f32);
+        let i = (gx.floor() as usize).min(self.width - 2);
+        let j = (gz.floor() as usize).min(self.depth - 2);
+        let (fx, fz) = (gx - i as f32, gz - j as f32);
+        let n = |i: usize, j: usize| self.normals[j * self.width + i];
+        if fx + fz <= 1.0 {
+            n(i, j) * (1.0 - fx - fz) + n(i + 1, j) * fx + n(i, j + 1) * fz
+        } else {
+            n(i + 1, j + 1) * (fx + fz - 1.0) + n(i, j + 1) * (1.0 - fx) + n(i + 1, j) * (1.0 - fz)
+        }
+    }
+    /// Intersect a ray in grid units with the two triangles of a cell
+    fn intersect_cell(&self, origin: Tuple, direction: Tuple, i: usize, j: usize) -> Vec<f32> {
+        let (h00, h10, h01, h11) = (
+            self.height(i, j),
+            self.height(i + 1, j),
+            self.height(i, j + 1),
+            self.height(i + 1, j + 1),
+        );
+        let mut hits = Vec::new();
+        // Each triangle is part of the plane y = h + dx * fx + dz * fz over the cell
+        for (first, h, dx, dz) in [
+            (true, h00, h10 - h00, h01 - h00),
+            (false, h10 + h01 - h11, h11 - h01, h11 - h10),
+        ] {
+            let (ox, oz) = (origin.x - i as f32, origin.z - j as f32);
+            let denominator = direction.y - dx * direction.x - dz * direction.z;
+            if denominator.abs() < f32::EPSILON {
+                continue;
+            }
+            let t = (h + dx * ox + dz * oz - origin.y) / denominator;
+            let (fx, fz) = (ox + direction.x * t, oz + direction.z * t);
+            let in_cell = (0.0..=1.0).contains(&fx) && (0.0..=1.0).contains(&fz);
+            if in_cell && (fx + fz <= 1.0) == first {
+                hits.push(t);
+            }
+        }
+        hits
+    }
+    /// The normal at a grid point from central differences of the neighbouring heights, in object space
+    fn vertex_normal(&self, i: usize, j: usize) -> Tuple {
+        let (il, ir) = (i.saturating_sub(1), (i + 1).min(self.width - 1));
+        let (jl, jr) = (j.saturating_sub(1), (j + 1).min(self.depth - 1));
+        let slope_x =
+            (self.height(ir, j) - self.height(il, j)) / (ir - il) as f32 * (self.width - 1) as f32;
+        let slope_z =
+            (self.height(i, jr) - self.height(i, jl)) / (jr - jl) as f32 * (self.depth - 1) as f32;
+        vector(-slope_x, 1.0, -slope_z).normalize()
+    }
+}
+/// The range of t where a ray is between low and high along one axis
+fn slab(origin: f32, direction: f32, low: f32, high: f32) -> (f32, f32) {
+    if direction.abs() < f32::EPSILON {
+        if origin < low || origin > high {
+            return (f32::INFINITY, f32::NEG_INFINITY);
+        }
+        return (f32::NEG_INFINITY, f32::INFINITY);
+    }
+    let (a, b) = ((low - origin) / direction, (high - origin) / direction);
+    (a.min(b), a.max(b))
+}
+/// The step between cells, the t of the first cell boundary and the t between boundaries along one grid axis
+fn dda_axis(origin: f32, direction: f32, cell: isize) -> (isize, f32, f32) {
+    if direction.abs() < f32::EPSILON {
+        return (0, f32::INFINITY, f32::INFINITY);
+    }
+    let step = if direction > 0.0 { 1 } else { -1 };
+    let boundary = if direction > 0.0 { cell + 1 } else { cell } as f32;
+    (step, (boundary - origin) / direction, 1.0 / direction.abs())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_1_SQRT_2;
+
+    use approx::assert_relative_eq;
+
+    use super::Heightfield;
+    use crate::{
+        canvas::Canvas,
+        colour::{Colour, BLACK, WHITE},
+        noise::fbm,
+        tuple::{point, vector},
+        DEFAULT_EPSILON,
+    };
+
+    #[test]
+    fn heights_from_canvas_and_function() {
+        let mut image = Canvas::new(3, 2, BLACK);
+        image.write_pixel(2, 1, WHITE);
+        image.write_pixel(1, 0, Colour::new(0.5, 0.5, 0.5));
+        let field = Heightfield::from_canvas(&image).unwrap();
+        assert_relative_eq!(field.height(2, 1), 1.0, epsilon = DEFAULT_EPSILON);
+        assert_relative_eq!(field.height(1, 0), 0.5, epsilon = DEFAULT_EPSILON);
+        assert_eq!(field.height(0, 0), 0.0);
+        assert!(Heightfield::from_canvas(&Canvas::new(1, 4, WHITE)).is_err());
+        assert!(Heightfield::new(3, 2, vec![0.0; 5]).is_err());
+        let field = Heightfield::from_fn(5, 3, |x, z| x + 2.0 * z).unwrap();
+        assert_eq!(field.height(4, 0), 1.0);
+        assert_eq!(field.height(2, 2), 2.5);
+        // Noise functions work as height sources
+        let terrain =
+            Heightfield::from_fn(32, 32, |x, z| fbm(point(x * 4.0, 0.0, z * 4.0), 4)).unwrap();
+        assert_eq!(
+            terrain.height(7, 9),
+            fbm(point(7.0 / 31.0 * 4.0, 0.0, 9.0 / 31.0 * 4.0), 4)
+        );
+    }
+    #[test]
+    fn intersect_tilted_field() {
+        // A slope rising along x, y = x
+        let field = Heightfield::from_fn(9, 9, |x, _| x).unwrap();
+        let xs = field.intersect(point(0.3, 5.0, 0.6), vector(0.0, -1.0, 0.0));
+        assert_eq!(xs.len(), 1);
+        assert_relative_eq!(xs[0], 4.7, epsilon = DEFAULT_EPSILON * 10.0);
+        // A grazing ray crossing many cells still finds the single crossing
+        let xs = field.intersect(point(-1.0, 0.25, 0.45), vector(1.0, 0.0, 0.01));
+        assert_eq!(xs.len(), 1);
+        assert_relative_eq!(xs[0], 1.25, epsilon = DEFAULT_EPSILON * 10.0);
+        // Outside the grid
+        assert!(field
+            .intersect(point(1.5, 5.0, 0.5), vector(0.0, -1.0, 0.0))
+            .is_empty());
+        assert!(field
+            .intersect(point(0.5, 2.0, 0.5), vector(1.0, 0.0, 0.0))
+            .is_empty());
+    }
+    #[test]
+    fn ray_crosses_ridge_twice() {
+        // A ridge along z with its peak at x = 0.5
+        let field = Heightfield::from_fn(11, 5, |x, _| 1.0 - (2.0 * x - 1.0).abs()).unwrap();
+        let xs = field.intersect(point(-1.0, 0.5, 0.3), vector(1.0, 0.0, 0.0));
+        assert_eq!(xs.len(), 2);
+        assert_relative_eq!(xs[0], 1.25, epsilon = DEFAULT_EPSILON * 10.0);
+        assert_relative_eq!(xs[1], 1.75, epsilon = DEFAULT_EPSILON * 10.0);
+    }
+    #[test]
+    fn interpolated_normals() {
+        let flat = Heightfield::from_fn(4, 4, |_, _| 0.5).unwrap();
+        assert_relative_eq!(
+            flat.normal(point(0.4, 0.5, 0.7)),
+            vector(0.0, 1.0, 0.0),
+            epsilon = DEFAULT_EPSILON
+        );
+        let slope = Heightfield::from_fn(5, 5, |x, _| x).unwrap();
+        let n = slope.normal(point(0.6, 0.6, 0.2)).normalize();
+        assert_relative_eq!(
+            n,
+            vector(-1.0, 1.0, 0.0).normalize(),
+            epsilon = DEFAULT_EPSILON
+        );
+        // The normal turns smoothly over the peak of a ridge
+        let ridge = Heightfield::from_fn(11, 5, |x, _| 1.0 - (2.0 * x - 1.0).abs()).unwrap();
+        assert_relative_eq!(
+            ridge.normal(point(0.5, 1.0, 0.5)).normalize(),
+            vector(0.0, 1.0, 0.0),
+            epsilon = DEFAULT_EPSILON
+        );
+        let n = ridge.normal(point(0.45, 0.9, 0.5)).normalize();
+        assert!(n.x < 0.0 && n.x > -FRAC_1_SQRT_2);
+    }
+}
@@ -0,0 +1,319 @@
+use crate::{
+    colour::{Colour, BLACK, WHITE},
+    ray::{direct_lighting, schlick, Computations, Ray},
+    sampling::{cosine_hemisphere, random},
+    tuple::Tuple,
+    world::{volume_span, World, VOLUME_EPSILON},
+};
+
+/// Bounces after which a path may be ended early by Russian roulette.
+const ROULETTE_DEPTH: usize = 3;
+/// The lowest chance of a path surviving Russian roulette, stops dim paths from being weighted too heavily.
+const MIN_SURVIVAL: f32 = 0.05;
+
+/// How a render turns camera rays into colours
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum Integrator {
+    /// Recursive ray tracing with a constant ambient term.
+    #[default]
+    Whitted,
+    /// Monte Carlo path tracing, averaging `samples` jittered paths per pixel of at most `max_depth` bounces.
+    /// The ambient term is replaced by light bounced from other surfaces.
+    PathTraced { samples: usize, max_depth: usize },
+    /// Shade every surface by how open it is, white for fully open and black for fully enclosed, misses are white.
+    AmbientOcclusion(AmbientOcclusion),
+}
+
+/// Darken the ambient light in crevices and at contact points by casting rays into the hemisphere above a hit
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct AmbientOcclusion {
+    pub samples: usize,
+    /// Hits further away than this do not occlude.
+    pub distance: f32,
+}
+impl AmbientOcclusion {
+    /// Create an ambient occlusion setting from the number of rays per hit and how far they reach
+    pub fn new(samples: usize, distance: f32) -> AmbientOcclusion {
+        AmbientOcclusion {
+            samples: samples.max(1),
+            distance,
+        }
+    }
+    /// The cosine weighted fraction of the hemisphere around normal that is open, from 0.0 to 1.0
+    pub fn visibility(&self, world: &World, point: Tuple, normal: Tuple) -> f32 {
+        let mut open = 0;
+        for _ in 0..self.samples {
+            let r = Ray::new(point, cosine_hemisphere(normal));
+            match world.intersect(&r).hit() {
+                Some(hit) if hit.t < self.distance => {}
+                _ => open += 1,
+            }
+        }
+        open as f32 / self.samples as f32
+    }
+}
+
+/// Follow one random light path from the ray and return the light it carries back.
+/// At every hit the lights are sampled directly, then the path continues as a mirror reflection, a refraction
+/// or a cosine weighted diffuse bounce, chosen with the material's reflective, transparency and diffuse values.
+/// If those add up to more than 1.0 they are scaled down so a path never gains energy.
+/// The fog and volume objects dim every segment of the path and add the light they scatter along it,
+/// and the path carries straight on through volume objects rather than bouncing off them.
+pub fn trace_path(world: &World, ray: Ray, max_depth: usize) -> Colour {
+    let mut radiance = BLACK;
+    let mut throughput = WHITE;
+    let mut ray = ray;
+    // The environment light is sampled directly at diffuse hits, so paths escaping from them must not add it again
+    let mut diffuse_bounce = false;
+    for bounce in 0..max_depth {
+        let inters = world.intersect(&ray);
+        let hit = inters.hit();
+        let volume = hit
+            .as_ref()
+            .filter(|hit| hit.object.material.volume.is_some())
+            .map(|hit| volume_span(&ray, hit, &inters));
+        // How far the ray travels before the path continues
+        let distance = match (&hit, volume) {
+            (None, _) => f32::INFINITY,
+            (Some(_), Some((_, end))) => end + VOLUME_EPSILON,
+            (Some(hit), None) => hit.t,
+        };
+        if let Some(fog) = &world.fog {
+            radiance = radiance + throughput * world.through_medium(fog, ray, 0.0, distance, BLACK);
+            throughput = throughput * fog.transmittance(distance);
+        }
+        let hit = match hit {
+            Some(hit) => hit,
+            None => {
+                if !(diffuse_bounce && world.environment_light.is_some()) {
+                    radiance = radiance + throughput * world.background.colour_at(ray.direction);
+                }
+                break;
+            }
+        };
+        if let (Some(medium), Some((start, end))) = (&hit.object.material.volume, volume) {
+            radiance = radiance + throughput * world.through_medium(medium, ray, start, end, BLACK);
+            throughput = throughput * medium.transmittance(end - start);
+            let exit_point = ray.position(end) + ray.direction.normalize() * VOLUME_EPSILON;
+            ray = Ray::new(exit_point, ray.direction);
+            continue;
+        }
+        let comps = ray.prepare_computations(&hit, inters);
+        let material = comps.object.material.clone();
+        if comps.inside {
+            // The path has travelled through the object since it refracted in
+            throughput = throughput * material.transmittance(comps.t);
+        }
+        radiance = radiance + throughput * material.emission();
+        // Next event estimation, the light arriving straight from each light
+        for light in &world.lights {
+            let transmittance = world.shadow_transmittance(comps.over_point, light.position);
+            if transmittance != BLACK {
+                radiance = radiance
+                    + throughput
+                        * direct_lighting(
+                            &comps.object,
+                            *light,
+                            comps.over_point,
+                            comps.eyev,
+                            comps.normalv,
+                        )
+                        * transmittance;
+            }
+        }
+        radiance = radiance + throughput * world.image_based_lighting(&comps);
+        let (reflective, transparency) = if material.reflective > 0.0 && material.transparency > 0.0
+        {
+            let reflectance = schlick(comps.clone());
+            (
+                material.reflective * reflectance,
+                material.transparency * (1.0 - reflectance),
+            )
+        } else {
+            (material.reflective, material.transparency)
+        };
+        let total = (reflective + transparency + material.diffuse).max(1.0);
+        let choice = random() * total;
+        diffuse_bounce = choice >= reflective + transparency;
+        let (origin, mut direction) = if choice < reflective {
+            (comps.over_point, comps.reflectv)
+        } else if choice < reflective + transparency {
+            match refraction_direction(&comps) {
+                Some(direction) => (comps.under_point, direction),
+                None => (comps.over_point, comps.reflectv),
+            }
+        } else if choice < reflective + transparency + material.diffuse {
+            throughput = throughput * comps.object.pattern_at(comps.point);
+            (comps.over_point, cosine_hemisphere(comps.normalv))
+        } else {
+            break;
+        };
+        if choice < reflective + transparency {
+            if let Some(glossy) = &material.glossy {
+                direction = glossy.scatter(direction, comps.normalv);
+            }
+        }
+        if bounce + 1 >= ROULETTE_DEPTH {
+            let survival = throughput
+                .red
+                .max(throughput.green)
+                .max(throughput.blue)
+                .clamp(MIN_SURVIVAL, 1.0);
+            if random() >= survival {
+                break;
+            }
+            throughput = throughput * (1.0 / survival);
+        }
+        ray = Ray::new(origin, direction);
+    }
+    radiance
+}
+/// The direction of the ray refracted at a hit, None for total internal reflection
+pub(crate) fn refraction_direction(comps: &Computations) -> Option<Tuple> {
+    let n_ratio = comps.n1 / comps.n2;
+    let cos_i = comps.eyev.dot(comps.normalv);
+    let sin2_t = f32::powi(n_ratio, 2) * (1.0 - f32::powi(cos_i, 2));
+    if sin2_t > 1.0 {
+        return None;
+    }
+    let cos_t = f32::sqrt(1.0 - sin2_t);
+    Some(comps.normalv * (n_ratio * cos_i - cos_t) - comps.eyev * n_ratio)
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+
+    use super::{trace_path, AmbientOcclusion, Integrator};
+    use crate::{
+        colour::{Colour, BLACK, WHITE},
+        ray::{Light, Ray},
+        shapes::Object,
+        transformation::{rot_x, scale, translation},
+        tuple::{point, vector},
+        volume::Medium,
+        world::World,
+        DEFAULT_EPSILON,
+    };
+    use std::f32::consts::PI;
+
+    #[test]
+    fn path_sees_emissive_object() {
+        let mut world = World::new();
+        let mut lamp = Object::new_sphere();
+        lamp.material.emissive = Colour::new(1.0, 0.5, 0.25);
+        lamp.material.emission_strength = 2.0;
+        lamp.material.diffuse = 0.0;
+        world.objects.push(lamp);
+        let r = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        assert_relative_eq!(
+            trace_path(&world, r, 5),
+            Colour::new(2.0, 1.0, 0.5),
+            epsilon = DEFAULT_EPSILON
+        );
+        assert_eq!(trace_path(&world, r, 0), BLACK);
+    }
+    #[test]
+    fn path_matches_direct_light_on_convex_object() {
+        // Bounces from the outside of a lone sphere escape, so only the light sampled directly remains
+        let mut world = World::default_world();
+        world.objects[0].material.ambient = 0.0;
+        let r = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        let expected = world.colour_at(r, 5);
+        for _ in 0..20 {
+            assert_relative_eq!(
+                trace_path(&world, r, 5),
+                expected,
+                epsilon = DEFAULT_EPSILON
+            );
+        }
+    }
+    #[test]
+    fn colour_bleeds_between_diffuse_surfaces() {
+        let mut world = World::new();
+        world.lights.push(Light::new(point(5.0, 10.0, -5.0), WHITE));
+        let floor = Object::new_plane();
+        world.objects.push(floor);
+        let mut ball = Object::new_sphere();
+        ball.transform = translation(0.0, 1.0, 0.0);
+        ball.material.colour = Colour::new(1.0, 0.0, 0.0);
+        world.objects.push(ball);
+        let r = Ray::new(
+            point(1.2, 1.0, -1.0),
+            (point(1.2, 0.0, 0.0) - point(1.2, 1.0, -1.0)).normalize(),
+        );
+        let mut total = BLACK;
+        for _ in 0..2000 {
+            total = total + trace_path(&world, r, 4);
+        }
+        // The white floor picks up red light bounced off the ball
+        assert!(total.red > total.blue * 1.05);
+    }
+    #[test]
+    fn path_travels_through_fog_and_volumes() {
+        let mut world = World::new();
+        world.lights.push(Light::new(point(0.0, 10.0, 0.0), WHITE));
+        let mut backdrop = Object::new_plane();
+        backdrop.transform = translation(0.0, 0.0, 10.0) * rot_x(PI / 2.0);
+        backdrop.material.emissive = WHITE;
+        backdrop.material.ambient = 0.0;
+        backdrop.material.diffuse = 0.0;
+        backdrop.material.specular = 0.0;
+        world.objects.push(backdrop);
+        let mut smoke = Object::new_sphere();
+        smoke.material.volume = Some(Medium::new(Colour::new(0.5, 0.5, 0.5), 0.5));
+        world.objects.push(smoke);
+        world.fog = Some(Medium::new(WHITE, 0.05));
+        let r = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        let expected = world.colour_at(r, 5);
+        world.integrator = Integrator::PathTraced {
+            samples: 1,
+            max_depth: 5,
+        };
+        // Only the sample points within each step of the media are random
+        let mut total = BLACK;
+        for _ in 0..200 {
+            total = total + trace_path(&world, r, 5);
+        }
+        assert_relative_eq!(total * (1.0 / 200.0), expected, epsilon = 0.01);
+    }
+    #[test]
+    fn open_surface_is_not_occluded() {
+        let mut world = World::new();
+        world.objects.push(Object::new_plane());
+        let ao = AmbientOcclusion::new(64, 10.0);
+        assert_eq!(
+            ao.visibility(&world, point(0.0, 0.001, 0.0), vector(0.0, 1.0, 0.0)),
+            1.0
+        );
+    }
+    #[test]
+    fn enclosed_surface_is_occluded() {
+        let mut world = World::new();
+        let mut room = Object::new_sphere();
+        room.transform = scale(5.0, 5.0, 5.0);
+        world.objects.push(room);
+        let ao = AmbientOcclusion::new(64, 20.0);
+        assert_eq!(
+            ao.visibility(&world, point(0.0, 0.0, 0.0), vector(0.0, 1.0, 0.0)),
+            0.0
+        );
+        // The walls are out of reach of short rays
+        let ao = AmbientOcclusion::new(64, 2.0);
+        assert_eq!(
+            ao.visibility(&world, point(0.0, 0.0, 0.0), vector(0.0, 1.0, 0.0)),
+            1.0
+        );
+    }
+    #[test]
+    fn crease_is_partly_occluded() {
+        let mut world = World::new();
+        world.objects.push(Object::new_plane());
+        let mut ball = Object::new_sphere();
+        ball.transform = translation(0.0, 1.0, 0.0);
+        world.objects.push(ball);
+        let ao = AmbientOcclusion::new(2000, 10.0);
+        let visibility = ao.visibility(&world, point(1.1, 0.001, 0.0), vector(0.0, 1.0, 0.0));
+        assert!(visibility > 0.3 && visibility < 0.9);
+    }
+}
@@ -1,11 +1,26 @@
+pub mod bump;
 pub mod canvas;
 pub mod colour;
+pub mod dispersion;
+pub mod environment;
+pub mod heightfield;
+pub mod integrator;
 pub mod matrix;
+pub mod noise;
+pub mod pbr;
+pub mod photon;
+pub mod polynomial;
 pub mod projectile;
+pub mod ramp;
 pub mod ray;
+pub mod sampling;
+pub mod sdf;
 pub mod shapes;
+pub mod sky;
+pub mod texture;
 pub mod transformation;
 pub mod tuple;
+pub mod volume;
 pub mod world;
 pub mod run {
     use crate::colour::{Colour, BLACK, BLUE, GREEN, RED, WHITE, YELLOW};
@@ -95,7 +110,7 @@ pub mod run {
                     let point = r.position(hit_inter.t);
                     let normal = hit_inter.object.normal_at(point);
                     let eye = -(r.direction);
-                    let colour = lighting(hit_inter.object, light, point, eye, normal, false);
+                    let colour = lighting(&hit_inter.object, light, point, eye, normal, WHITE);
                     canv.write_pixel(_x, _y, colour);
                 }
             }
@@ -110,6 +125,7 @@ pub mod run {
         let world = World {
             objects: vec![red_sphere],
             lights: vec![light],
+            ..World::new()
         };
         let mut cam = Camera::new(1000, 1000, PI / 5.0);
         let from = point(0.0, 0.0, -5.0);
@@ -160,8 +176,9 @@ pub mod run {
 
         let objects = vec![floor, l_wall, r_wall, middle, right, left];
         let world = World {
-            objects: objects,
+            objects,
             lights: vec![light],
+            ..World::new()
         };
         let mut cam = Camera::new(2000, 1000, PI / 3.0);
         let from = point(0.0, 1.5, -5.0);
@@ -182,11 +199,8 @@ pub mod run {
         let mut l_wall = Object::new_plane();
         l_wall.transform = translation(0.0, 0.0, 5.0) * rot_y((-PI) / 4.0) * rot_x(PI / 2.0);
 
-        let mut r_wall = Object::new_plane();
-        r_wall.transform = translation(0.0, 0.0, 5.0)
-            * rot_y(PI / 4.0)
-            * rot_x(PI / 2.0)
-            * scale(10.0, 0.01, 10.0);
+        let mut r_wall = Object::new_rectangle(20.0, 20.0);
+        r_wall.transform = translation(0.0, 0.0, 5.0) * rot_y(PI / 4.0) * rot_x(PI / 2.0);
 
         let mut middle = Object::new_sphere();
         middle.transform = translation(-0.5, 1.0, 0.5);
@@ -208,8 +222,9 @@ pub mod run {
 
         let objects = vec![floor, l_wall, r_wall, middle, right, left];
         let world = World {
-            objects: objects,
+            objects,
             lights: vec![light],
+            ..World::new()
         };
         let mut cam = Camera::new(2000, 1000, PI / 3.0);
         let from = point(0.0, 1.5, -5.0);
@@ -255,15 +270,16 @@ pub mod run {
         let mut left = Object::new_sphere();
         left.transform = translation(-1.5, 0.33, -0.75) * scale(0.33, 0.33, 0.33);
         let mut p = Pattern::new_gradient(BLUE, WHITE);
-        p.transformation =  rot_z(PI / 4.0) * scale(2.0, 2.0, 2.0) * translation(0.5, 0.0, 0.0);
+        p.transformation = rot_z(PI / 4.0) * scale(2.0, 2.0, 2.0) * translation(0.5, 0.0, 0.0);
         left.material.pattern = Some(p);
         left.material.diffuse = 0.7;
         left.material.specular = 0.3;
 
         let objects = vec![floor, l_wall, r_wall, middle, right, left];
         let world = World {
-            objects: objects,
+            objects,
             lights: vec![light],
+            ..World::new()
         };
         let mut cam = Camera::new(2000, 1000, PI / 3.0);
         let from = point(0.0, 1.5, -5.0);
@@ -286,7 +302,7 @@ pub mod run {
         let mut l_wall = Object::new_plane();
         l_wall.transform = translation(0.0, 0.0, 5.0) * rot_y((-PI) / 4.0) * rot_x(PI / 2.0);
         let mut p = Pattern::new_striped(BLUE, RED);
-        p.transformation = rot_y(PI/8.0);
+        p.transformation = rot_y(PI / 8.0);
         l_wall.material.pattern = Some(p);
 
         let mut r_wall = Object::new_plane();
@@ -295,7 +311,6 @@ pub mod run {
 
         let mut middle = Object::glass_sphere();
         middle.transform = translation(-0.5, 1.0, 0.5);
-        p.transformation = translation(0.6, 0.0, 0.0) * scale(0.5, 0.5, 0.5);
         middle.material.ambient = 0.05;
         middle.material.diffuse = 0.1;
         middle.material.specular = 1.0;
@@ -316,7 +331,7 @@ pub mod run {
         let mut left = Object::new_sphere();
         left.transform = translation(-1.5, 0.33, -0.75) * scale(0.33, 0.33, 0.33);
         let mut p = Pattern::new_gradient(BLUE, WHITE);
-        p.transformation =  rot_z(PI / 4.0) * scale(2.0, 2.0, 2.0) * translation(0.5, 0.0, 0.0);
+        p.transformation = rot_z(PI / 4.0) * scale(2.0, 2.0, 2.0) * translation(0.5, 0.0, 0.0);
         left.material.pattern = Some(p);
         left.material.diffuse = 0.7;
         left.material.specular = 0.3;
@@ -330,8 +345,9 @@ pub mod run {
 
         let objects = vec![floor, l_wall, r_wall, middle, right, left, mirror_ball];
         let world = World {
-            objects: objects,
+            objects,
             lights: vec![light],
+            ..World::new()
         };
         let mut cam = Camera::new(2000, 1000, PI / 3.0);
         let from = point(0.0, 1.5, -5.0);
@@ -339,8 +355,14 @@ pub mod run {
         let up = vector(0.0, 1.0, 0.0);
         cam.transform = view_transform(from, to, up);
         let image = world.render(cam);
-        let time_stamp = chrono::offset::Local::now().format("%Y-%m-%d_%H-%M-%S").to_string();
-        fs::write(format!("renders/scene_render_{}.ppm", time_stamp), image.to_ppm()).expect("Error writing image to disk");
+        let time_stamp = chrono::offset::Local::now()
+            .format("%Y-%m-%d_%H-%M-%S")
+            .to_string();
+        fs::write(
+            format!("renders/scene_render_{}.ppm", time_stamp),
+            image.to_ppm(),
+        )
+        .expect("Error writing image to disk");
     }
 }
 const DEFAULT_EPSILON: f32 = 0.00001; //TODO does this belong here?
@@ -0,0 +1,383 @@
+use crate::tuple::{point, vector, Tuple};
+
+/// Ken Perlin's reference permutation table.
+const PERMUTATION: [u8; 256] = [
+    151, 160, 137, 91, 90, 15, 131, 13, 201, 95, 96, 53, 194, 233, 7, 225, 140, 36, 103, 30, 69,
+    142, 8, 99, 37, 240, 21, 10, 23, 190, 6, 148, 247, 120, 234, 75, 0, 26, 197, 62, 94, 252, 219,
+    203, 117, 35, 11, 32, 57, 177, 33, 88, 237, 149, 56, 87, 174, 20, 125, 136, 171, 168, 68, 175,
+    74, 165, 71, 134, 139, 48, 27, 166, 77, 146, 158, 231, 83, 111, 229, 122, 60, 211, 133, 230,
+    220, 105, 92, 41, 55, 46, 245, 40, 244, 102, 143, 54, 65, 25, 63, 161, 1, 216, 80, 73, 209, 76,
+    132, 187, 208, 89, 18, 169, 200, 196, 135, 130, 116, 188, 159, 86, 164, 100, 109, 198, 173,
+    186, 3, 64, 52, 217, 226, 250, 124, 123, 5, 202, 38, 147, 118, 126, 255, 82, 85, 212, 207, 206,
+    59, 227, 47, 16, 58, 17, 182, 189, 28, 42, 223, 183, 170, 213, 119, 248, 152, 2, 44, 154, 163,
+    70, 221, 153, 101, 155, 167, 43, 172, 9, 129, 22, 39, 253, 19, 98, 108, 110, 79, 113, 224, 232,
+    178, 185, 112, 104, 218, 246, 97, 228, 251, 34, 242, 193, 238, 210, 144, 12, 191, 179, 162,
+    241, 81, 51, 145, 235, 249, 14, 239, 107, 49, 192, 214, 31, 181, 199, 106, 157, 184, 84, 204,
+    176, 115, 121, 50, 45, 127, 4, 150, 254, 138, 236, 205, 93, 222, 114, 67, 29, 24, 72, 243, 141,
+    128, 195, 78, 66, 215, 61, 156, 180,
+];
+/// Look up the permutation table, wrapping the index into range
+fn perm(i: i32) -> i32 {
+    PERMUTATION[(i & 255) as usize] as i32
+}
+/// Smooth the interpolation weight so the noise has continuous derivatives
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+fn lerp(t: f32, a: f32, b: f32) -> f32 {
+    a + t * (b - a)
+}
+/// Dot product of the offset with one of twelve gradient directions picked by the hash
+fn grad(hash: i32, x: f32, y: f32, z: f32) -> f32 {
+    let h = hash & 15;
+    let u = if h < 8 { x } else { y };
+    let v = if h < 4 {
+        y
+    } else if h == 12 || h == 14 {
+        x
+    } else {
+        z
+    };
+    (if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+}
+/// Improved Perlin gradient noise at a point. The result is roughly between -1.0 and 1.0 and is 0.0 at integer coordinates.
+pub fn perlin(point: Tuple) -> f32 {
+    let xi = point.x.floor() as i32;
+    let yi = point.y.floor() as i32;
+    let zi = point.z.floor() as i32;
+    let x = point.x - point.x.floor();
+    let y = point.y - point.y.floor();
+    let z = point.z - point.z.floor();
+    let u = fade(x);
+    let v = fade(y);
+    let w = fade(z);
+    let a = perm(xi) + yi;
+    let aa = perm(a) + zi;
+    let ab = perm(a + 1) + zi;
+    let b = perm(xi + 1) + yi;
+    let ba = perm(b) + zi;
+    let bb = perm(b + 1) + zi;
+    lerp(
+        w,
+        lerp(
+            v,
+            lerp(u, grad(perm(aa), x, y, z), grad(perm(ba), x - 1.0, y, z)),
+            lerp(
+                u,
+                grad(perm(ab), x, y - 1.0, z),
+                grad(perm(bb), x - 1.0, y - 1.0, z),
+            ),
+        ),
+        lerp(
+            v,
+            lerp(
+                u,
+                grad(perm(aa + 1), x, y, z - 1.0),
+                grad(perm(ba + 1), x - 1.0, y, z - 1.0),
+            ),
+            lerp(
+                u,
+                grad(perm(ab + 1), x, y - 1.0, z - 1.0),
+                grad(perm(bb + 1), x - 1.0, y - 1.0, z - 1.0),
+            ),
+        ),
+    )
+}
+/// Fractional Brownian motion, the sum of octaves of Perlin noise each at double the frequency and half the amplitude.
+/// The result is normalised to roughly between -1.0 and 1.0.
+pub fn fbm(point: Tuple, octaves: usize) -> f32 {
+    let mut sum = 0.0;
+    let mut amplitude = 1.0;
+    let mut total_amplitude = 0.0;
+    let mut p = point;
+    for _ in 0..octaves {
+        sum += perlin(p) * amplitude;
+        total_amplitude += amplitude;
+        amplitude *= 0.5;
+        p = p * 2.0;
+        p.w = point.w;
+    }
+    if total_amplitude > 0.0 {
+        sum / total_amplitude
+    } else {
+        0.0
+    }
+}
+/// Turbulence, like fbm but summing the absolute value of each octave. The result is roughly between 0.0 and 1.0.
+pub fn turbulence(point: Tuple, octaves: usize) -> f32 {
+    let mut sum = 0.0;
+    let mut amplitude = 1.0;
+    let mut total_amplitude = 0.0;
+    let mut p = point;
+    for _ in 0..octaves {
+        sum += perlin(p).abs() * amplitude;
+        total_amplitude += amplitude;
+        amplitude *= 0.5;
+        p = p * 2.0;
+        p.w = point.w;
+    }
+    if total_amplitude > 0.0 {
+        sum / total_amplitude
+    } else {
+        0.0
+    }
+}
+/// A vector of three uncorrelated fbm values, used to jitter points
+pub fn fbm_vector(point: Tuple, octaves: usize) -> Tuple {
+    vector(
+        fbm(point, octaves),
+        fbm(point + vector(31.416, 47.853, 12.793), octaves),
+        fbm(point + vector(-71.337, 5.113, -23.719), octaves),
+    )
+}
+/// How the distance between a point and the cell feature points is measured.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum DistanceMetric {
+    Euclidean,
+    /// Sum of the distances along each axis, gives diamond shaped cells.
+    Manhattan,
+    /// Largest distance along any axis, gives square cells.
+    Chebyshev,
+}
+impl DistanceMetric {
+    /// The distance between two points
+    pub fn distance(&self, a: Tuple, b: Tuple) -> f32 {
+        let d = a - b;
+        match self {
+            DistanceMetric::Euclidean => (d.x * d.x + d.y * d.y + d.z * d.z).sqrt(),
+            DistanceMetric::Manhattan => d.x.abs() + d.y.abs() + d.z.abs(),
+            DistanceMetric::Chebyshev => d.x.abs().max(d.y.abs()).max(d.z.abs()),
+        }
+    }
+}
+/// Which value of cellular noise a pattern uses.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum CellularOutput {
+    /// Distance to the closest feature point, gives round cells.
+    F1,
+    /// Distance to the second closest feature point.
+    F2,
+    /// Difference between F2 and F1, zero along the cell borders like cracks.
+    F2MinusF1,
+}
+/// Scramble the integer coordinates of a cell into a hash
+fn hash_cell(x: i32, y: i32, z: i32, seed: u32) -> u32 {
+    let mut h = (x as u32).wrapping_mul(73_856_093)
+        ^ (y as u32).wrapping_mul(19_349_663)
+        ^ (z as u32).wrapping_mul(83_492_791)
+        ^ seed.wrapping_mul(2_654_435_761);
+    h ^= h >> 13;
+    h = h.wrapping_mul(0x5bd1_e995);
+    h ^= h >> 15;
+    h
+}
+/// The feature point of the unit cell with the given integer coordinates
+fn feature_point(x: i32, y: i32, z: i32) -> Tuple {
+    let offset = |seed| (hash_cell(x, y, z, seed) & 0xffff) as f32 / 65536.0;
+    point(
+        x as f32 + offset(0),
+        y as f32 + offset(1),
+        z as f32 + offset(2),
+    )
+}
+/// Worley cellular noise. Each unit cell holds one random feature point and the result is the
+/// distance from the point to the closest (F1) and second closest (F2) feature points.
+pub fn worley(point: Tuple, metric: DistanceMetric) -> (f32, f32) {
+    let cx = point.x.floor() as i32;
+    let cy = point.y.floor() as i32;
+    let cz = point.z.floor() as i32;
+    let mut f1 = f32::INFINITY;
+    let mut f2 = f32::INFINITY;
+    // Search rings of cells outwards from the point's cell. Cells in ring r are at least r - 1 away along
+    // some axis and no metric is shorter than that, so once that reaches F2 no further cell can be closer.
+    let mut ring: i32 = 0;
+    while ((ring - 1) as f32) < f2 {
+        for dx in -ring..=ring {
+            for dy in -ring..=ring {
+                for dz in -ring..=ring {
+                    if dx.abs().max(dy.abs()).max(dz.abs()) != ring {
+                        continue;
+                    }
+                    let d = metric.distance(point, feature_point(cx + dx, cy + dy, cz + dz));
+                    if d < f1 {
+                        f2 = f1;
+                        f1 = d;
+                    } else if d < f2 {
+                        f2 = d;
+                    }
+                }
+            }
+        }
+        ring += 1;
+    }
+    (f1, f2)
+}
+/// The value of cellular noise selected by output, divided by about the largest value it takes so that
+/// every metric and output fills the range 0.0 to 1.0
+pub fn cellular(point: Tuple, metric: DistanceMetric, output: CellularOutput) -> f32 {
+    let (f1, f2) = worley(point, metric);
+    // Manhattan distances run about half as long again as Euclidean ones, Chebyshev ones a little shorter
+    let scale = match metric {
+        DistanceMetric::Euclidean => 1.0,
+        DistanceMetric::Manhattan => 1.5,
+        DistanceMetric::Chebyshev => 0.85,
+    };
+    match output {
+        CellularOutput::F1 => f1 / scale,
+        CellularOutput::F2 => f2 / (1.2 * scale),
+        CellularOutput::F2MinusF1 => (f2 - f1) / scale,
+    }
+}
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+
+    use super::{
+        cellular, fbm, fbm_vector, feature_point, perlin, turbulence, worley, CellularOutput,
+        DistanceMetric,
+    };
+    use crate::tuple::{point, vector};
+
+    #[test]
+    fn perlin_is_zero_on_lattice() {
+        assert_eq!(perlin(point(0.0, 0.0, 0.0)), 0.0);
+        assert_eq!(perlin(point(3.0, -2.0, 7.0)), 0.0);
+    }
+    #[test]
+    fn perlin_is_bounded_and_varies() {
+        let mut min = f32::MAX;
+        let mut max = f32::MIN;
+        for i in 0..1000 {
+            let f = i as f32 * 0.173;
+            let n = perlin(point(f, f * 0.71 + 0.3, f * 1.37 - 0.6));
+            min = min.min(n);
+            max = max.max(n);
+        }
+        assert!(min >= -1.1 && max <= 1.1);
+        assert!(max - min > 0.5);
+    }
+    #[test]
+    fn perlin_is_continuous() {
+        let a = perlin(point(1.5, 2.25, 3.75));
+        let b = perlin(point(1.5001, 2.25, 3.75));
+        assert_relative_eq!(a, b, epsilon = 0.001);
+    }
+    #[test]
+    fn fbm_with_one_octave_is_perlin() {
+        let p = point(0.3, 1.7, -2.2);
+        assert_eq!(fbm(p, 1), perlin(p));
+        assert_eq!(fbm(p, 0), 0.0);
+    }
+    #[test]
+    fn turbulence_is_positive() {
+        for i in 0..100 {
+            let f = i as f32 * 0.37;
+            assert!(turbulence(point(f, -f, f * 0.5), 4) >= 0.0);
+        }
+    }
+    #[test]
+    fn fbm_vector_is_a_vector() {
+        let v = fbm_vector(point(0.4, 0.2, 0.9), 3);
+        assert!(v.is_vector());
+        assert!(v.x != v.y && v.y != v.z);
+    }
+    #[test]
+    fn distance_metrics() {
+        let a = point(0.0, 0.0, 0.0);
+        let b = point(1.0, -2.0, 2.0);
+        assert_eq!(DistanceMetric::Euclidean.distance(a, b), 3.0);
+        assert_eq!(DistanceMetric::Manhattan.distance(a, b), 5.0);
+        assert_eq!(DistanceMetric::Chebyshev.distance(a, b), 2.0);
+    }
+    #[test]
+    fn feature_points_lie_in_their_cell() {
+        for (x, y, z) in [(0, 0, 0), (-3, 5, 2), (10, -1, -7)] {
+            let p = feature_point(x, y, z);
+            assert!(p.x >= x as f32 && p.x < x as f32 + 1.0);
+            assert!(p.y >= y as f32 && p.y < y as f32 + 1.0);
+            assert!(p.z >= z as f32 && p.z < z as f32 + 1.0);
+        }
+    }
+    #[test]
+    fn worley_is_zero_at_feature_point() {
+        let p = feature_point(2, -1, 4);
+        let (f1, f2) = worley(p, DistanceMetric::Euclidean);
+        assert_eq!(f1, 0.0);
+        assert!(f2 > 0.0);
+        assert_eq!(
+            cellular(p, DistanceMetric::Euclidean, CellularOutput::F2MinusF1),
+            f2
+        );
+    }
+    #[test]
+    fn worley_orders_distances() {
+        for i in 0..50 {
+            let f = i as f32 * 0.29;
+            let p = point(f, f * 0.6 - 1.0, f * 1.3) + vector(0.1, 0.0, 0.0);
+            let (f1, f2) = worley(p, DistanceMetric::Euclidean);
+            assert!(f1 <= f2);
+            let (chebyshev, _) = worley(p, DistanceMetric::Chebyshev);
+            let (manhattan, _) = worley(p, DistanceMetric::Manhattan);
+            assert!(chebyshev <= f1 && f1 <= manhattan);
+        }
+    }
+    #[test]
+    fn worley_matches_wide_search() {
+        let metrics = [
+            DistanceMetric::Euclidean,
+            DistanceMetric::Manhattan,
+            DistanceMetric::Chebyshev,
+        ];
+        for i in 0..1000 {
+            let f = i as f32 * 0.37;
+            let p = point(f * 0.9, 3.0 - f * 0.4, f * 1.7);
+            for metric in metrics {
+                let mut distances = vec![];
+                for dx in -4..=4 {
+                    for dy in -4..=4 {
+                        for dz in -4..=4 {
+                            let q = feature_point(
+                                p.x.floor() as i32 + dx,
+                                p.y.floor() as i32 + dy,
+                                p.z.floor() as i32 + dz,
+                            );
+                            distances.push(metric.distance(p, q));
+                        }
+                    }
+                }
+                distances.sort_by(|a, b| a.partial_cmp(b).unwrap());
+                assert_eq!(worley(p, metric), (distances[0], distances[1]));
+            }
+        }
+    }
+    #[test]
+    fn cellular_output_fills_unit_range() {
+        let metrics = [
+            DistanceMetric::Euclidean,
+            DistanceMetric::Manhattan,
+            DistanceMetric::Chebyshev,
+        ];
+        let outputs = [
+            CellularOutput::F1,
+            CellularOutput::F2,
+            CellularOutput::F2MinusF1,
+        ];
+        for metric in metrics {
+            for output in outputs {
+                let mut saturated = 0;
+                let mut brightest: f32 = 0.0;
+                for i in 0..500 {
+                    let f = i as f32 * 0.113;
+                    let value = cellular(point(f, f * 0.7 - 2.0, 5.0 - f * 1.3), metric, output);
+                    assert!(value >= 0.0);
+                    brightest = brightest.max(value);
+                    if value >= 1.0 {
+                        saturated += 1;
+                    }
+                }
+                assert!(saturated <= 5, "{:?} {:?} saturates", metric, output);
+                assert!(brightest > 0.5, "{:?} {:?} stays dark", metric, output);
+            }
+        }
+    }
+}
@@ -0,0 +1,177 @@
+use std::f32::consts::PI;
+
+use crate::{
+    colour::{Colour, WHITE},
+    tuple::Tuple,
+};
+
+/// Reflectance at normal incidence of a typical dielectric such as plastic or glass.
+const DIELECTRIC_F0: f32 = 0.04;
+/// The smallest roughness used, a perfectly smooth GGX lobe is infinitely narrow.
+const MIN_ROUGHNESS: f32 = 0.02;
+
+/// A physically based metal/roughness material. The base colour comes from the material colour or pattern.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct PbrMaterial {
+    /// 0.0 for dielectrics, 1.0 for metals which tint their reflections and have no diffuse term.
+    pub metallic: f32,
+    /// Perceptual roughness from 0.0 (mirror like highlights) to 1.0 (very broad highlights).
+    pub roughness: f32,
+}
+impl PbrMaterial {
+    /// Create a new PBR material
+    pub fn new(metallic: f32, roughness: f32) -> PbrMaterial {
+        PbrMaterial {
+            metallic,
+            roughness,
+        }
+    }
+    /// Light reflected towards the eye from a single light using a Lambert diffuse and a Cook-Torrance GGX specular term.
+    /// The light intensity is treated as the irradiance received by a surface facing the light,
+    /// so a white non metallic surface facing a white light has the same diffuse brightness as with Phong.
+    pub fn direct(
+        &self,
+        base_colour: Colour,
+        intensity: Colour,
+        lightv: Tuple,
+        eyev: Tuple,
+        normalv: Tuple,
+    ) -> Colour {
+        let n_dot_l = normalv.dot(lightv);
+        let n_dot_v = normalv.dot(eyev);
+        if n_dot_l <= 0.0 || n_dot_v <= 0.0 {
+            return Colour::new(0.0, 0.0, 0.0);
+        }
+        let halfv = (lightv + eyev).normalize();
+        let n_dot_h = normalv.dot(halfv).max(0.0);
+        let h_dot_v = halfv.dot(eyev).max(0.0);
+        let roughness = self.roughness.clamp(MIN_ROUGHNESS, 1.0);
+        let f0 = WHITE * (DIELECTRIC_F0 * (1.0 - self.metallic)) + base_colour * self.metallic;
+        let fresnel = fresnel_schlick(f0, h_dot_v);
+        let specular = fresnel
+            * (ggx_distribution(n_dot_h, roughness) * smith_geometry(n_dot_v, n_dot_l, roughness)
+                / (4.0 * n_dot_v * n_dot_l));
+        let kd = (WHITE - fresnel) * (1.0 - self.metallic);
+        let diffuse = kd * base_colour * (1.0 / PI);
+        (diffuse + specular) * intensity * (PI * n_dot_l)
+    }
+}
+/// The GGX (Trowbridge-Reitz) normal distribution function
+pub fn ggx_distribution(n_dot_h: f32, roughness: f32) -> f32 {
+    let a = roughness * roughness;
+    let a2 = a * a;
+    let d = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+    a2 / (PI * d * d)
+}
+/// Smith's shadowing and masking term using the Schlick-GGX approximation for direct light
+pub fn smith_geometry(n_dot_v: f32, n_dot_l: f32, roughness: f32) -> f32 {
+    let k = (roughness + 1.0).powi(2) / 8.0;
+    let g1 = |x: f32| x / (x * (1.0 - k) + k);
+    g1(n_dot_v) * g1(n_dot_l)
+}
+/// Schlick's approximation of the Fresnel reflectance for a colour at normal incidence
+pub fn fresnel_schlick(f0: Colour, cos_theta: f32) -> Colour {
+    f0 + (WHITE - f0) * (1.0 - cos_theta).clamp(0.0, 1.0).powi(5)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::{FRAC_1_SQRT_2, PI};
+
+    use approx::assert_relative_eq;
+
+    use super::{fresnel_schlick, ggx_distribution, smith_geometry, PbrMaterial};
+    use crate::{
+        colour::{Colour, BLACK, WHITE},
+        ray::{lighting, Light},
+        shapes::Object,
+        tuple::{point, vector},
+        DEFAULT_EPSILON,
+    };
+
+    #[test]
+    fn ggx_integrates_to_one() {
+        // The projected distribution integrated over the hemisphere is 1.0
+        let roughness = 0.5;
+        let steps = 2000;
+        let mut sum = 0.0;
+        for i in 0..steps {
+            let theta = (i as f32 + 0.5) / steps as f32 * PI / 2.0;
+            sum += ggx_distribution(theta.cos(), roughness)
+                * theta.cos()
+                * theta.sin()
+                * 2.0
+                * PI
+                * (PI / 2.0 / steps as f32);
+        }
+        assert_relative_eq!(sum, 1.0, epsilon = 0.01);
+    }
+    #[test]
+    fn fresnel_at_normal_and_grazing() {
+        let f0 = Colour::new(0.04, 0.04, 0.04);
+        assert_relative_eq!(fresnel_schlick(f0, 1.0), f0);
+        assert_relative_eq!(fresnel_schlick(f0, 0.0), WHITE);
+    }
+    #[test]
+    fn smith_geometry_is_one_head_on() {
+        assert_relative_eq!(smith_geometry(1.0, 1.0, 0.7), 1.0);
+        assert!(smith_geometry(0.1, 1.0, 0.7) < 1.0);
+    }
+    #[test]
+    fn rough_dielectric_is_mostly_diffuse() {
+        let m = PbrMaterial::new(0.0, 1.0);
+        let n = vector(0.0, 0.0, -1.0);
+        let c = m.direct(WHITE, WHITE, n, n, n);
+        // Diffuse is scaled by 1 - F0, the rest is a weak specular lobe
+        assert!(c.red > 0.96 && c.red < 1.1);
+    }
+    #[test]
+    fn metal_has_no_diffuse() {
+        let m = PbrMaterial::new(1.0, 0.3);
+        let n = vector(0.0, 0.0, -1.0);
+        let off_specular = vector(0.0, FRAC_1_SQRT_2, -FRAC_1_SQRT_2);
+        let eyev = vector(0.0, -FRAC_1_SQRT_2, -FRAC_1_SQRT_2);
+        let dim = m.direct(
+            Colour::new(1.0, 0.5, 0.0),
+            WHITE,
+            off_specular,
+            vector(0.0, 1.0, -0.01).normalize(),
+            n,
+        );
+        let bright = m.direct(Colour::new(1.0, 0.5, 0.0), WHITE, off_specular, eyev, n);
+        assert!(bright.red > dim.red * 10.0);
+        // Only the Fresnel rise towards grazing angles adds any blue
+        assert!(bright.blue < bright.red * 0.01);
+        assert_relative_eq!(bright.green / bright.red, 0.5, epsilon = 0.05);
+    }
+    #[test]
+    fn light_behind_surface() {
+        let m = PbrMaterial::new(0.5, 0.5);
+        let n = vector(0.0, 0.0, -1.0);
+        assert_eq!(m.direct(WHITE, WHITE, vector(0.0, 0.0, 1.0), n, n), BLACK);
+    }
+    #[test]
+    fn lighting_uses_pbr_material() {
+        let mut o = Object::new_sphere();
+        o.material.pbr = Some(PbrMaterial::new(0.0, 1.0));
+        let light = Light::new(point(0.0, 0.0, -10.0), WHITE);
+        let eyev = vector(0.0, 0.0, -1.0);
+        let normalv = vector(0.0, 0.0, -1.0);
+        let p = point(0.0, 0.0, 0.0);
+        let expected = Colour::new(0.1, 0.1, 0.1)
+            + o.material
+                .pbr
+                .unwrap()
+                .direct(WHITE, WHITE, eyev, eyev, normalv);
+        assert_relative_eq!(
+            lighting(&o, light, p, eyev, normalv, WHITE),
+            expected,
+            epsilon = DEFAULT_EPSILON
+        );
+        assert_relative_eq!(
+            lighting(&o, light, p, eyev, normalv, BLACK),
+            Colour::new(0.1, 0.1, 0.1),
+            epsilon = DEFAULT_EPSILON
+        );
+    }
+}
@@ -0,0 +1,287 @@
+use std::f32::consts::PI;
+
+use crate::{
+    colour::Colour,
+    integrator::refraction_direction,
+    ray::{schlick, Ray},
+    sampling::{random, uniform_sphere},
+    tuple::Tuple,
+    world::World,
+};
+
+/// Bounces a photon may take before it is dropped.
+const MAX_BOUNCES: usize = 8;
+
+/// A packet of light that reached a diffuse surface after passing through or bouncing off specular objects
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Photon {
+    pub position: Tuple,
+    /// The direction the photon was travelling when it landed.
+    pub direction: Tuple,
+    pub power: Colour,
+}
+impl Photon {
+    pub fn new(position: Tuple, direction: Tuple, power: Colour) -> Photon {
+        Photon {
+            position,
+            direction,
+            power,
+        }
+    }
+}
+
+/// Caustic photons stored in a kd-tree, used to add light focused by glass and mirrors to diffuse surfaces
+#[derive(Debug, PartialEq, Clone)]
+pub struct PhotonMap {
+    /// Photons arranged as an implicit kd-tree, the median of each slice splits it on the axis for its depth.
+    photons: Vec<Photon>,
+    /// How far from a point photons are gathered when estimating its irradiance.
+    pub radius: f32,
+}
+impl PhotonMap {
+    /// Create a photon map from a list of photons
+    pub fn new(mut photons: Vec<Photon>, radius: f32) -> PhotonMap {
+        build(&mut photons, 0);
+        PhotonMap { photons, radius }
+    }
+    /// Emit count photons from every light in the world and keep those that land on a diffuse surface after
+    /// at least one reflection or refraction. The lights in this crate do not fall off with distance, so each
+    /// photon's power is scaled by the square of the length of its whole path when it is stored. A light seen
+    /// from that distance would give the surface the same irradiance as the direct lighting does.
+    pub fn trace(world: &World, count: usize, radius: f32) -> PhotonMap {
+        let mut photons = Vec::new();
+        for light in &world.lights {
+            for _ in 0..count {
+                let ray = Ray::new(light.position, uniform_sphere());
+                if let Some(photon) =
+                    trace_photon(world, ray, light.intensity * (4.0 * PI / count as f32))
+                {
+                    photons.push(photon);
+                }
+            }
+        }
+        PhotonMap::new(photons, radius)
+    }
+    pub fn len(&self) -> usize {
+        self.photons.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.photons.is_empty()
+    }
+    /// Find every photon within radius of point
+    pub fn photons_near(&self, point: Tuple, radius: f32) -> Vec<&Photon> {
+        let mut found = Vec::new();
+        gather(&self.photons, 0, point, radius * radius, &mut found);
+        found
+    }
+    /// Estimate the light arriving at a point on a surface facing along normal from the density of nearby photons
+    pub fn irradiance(&self, point: Tuple, normal: Tuple) -> Colour {
+        let mut total = Colour::new(0.0, 0.0, 0.0);
+        for photon in self.photons_near(point, self.radius) {
+            if photon.direction.dot(normal) < 0.0 {
+                total = total + photon.power;
+            }
+        }
+        total * (1.0 / (PI * self.radius * self.radius))
+    }
+}
+/// Follow a photon through the world, returning it if it lands on a diffuse surface after a specular bounce
+fn trace_photon(world: &World, ray: Ray, power: Colour) -> Option<Photon> {
+    let mut ray = ray;
+    let mut power = power;
+    let mut path_length = 0.0;
+    for bounce in 0..MAX_BOUNCES {
+        let inters = world.intersect(&ray);
+        let hit = inters.hit()?;
+        let comps = ray.prepare_computations(&hit, inters);
+        let material = comps.object.material.clone();
+        path_length += comps.t * ray.direction.magnitude();
+        if comps.inside {
+            power = power * material.transmittance(comps.t);
+        }
+        let (reflective, transparency) = if material.reflective > 0.0 && material.transparency > 0.0
+        {
+            let reflectance = schlick(comps.clone());
+            (
+                material.reflective * reflectance,
+                material.transparency * (1.0 - reflectance),
+            )
+        } else {
+            (material.reflective, material.transparency)
+        };
+        let specular = (reflective + transparency).min(1.0);
+        let choice = random();
+        if choice < reflective {
+            ray = Ray::new(comps.over_point, comps.reflectv);
+        } else if choice < specular {
+            ray = match refraction_direction(&comps) {
+                Some(direction) => Ray::new(comps.under_point, direction),
+                None => Ray::new(comps.over_point, comps.reflectv),
+            };
+        } else {
+            // Light that reaches a diffuse surface directly is handled by the lights themselves
+            if bounce == 0 || material.diffuse == 0.0 {
+                return None;
+            }
+            let stored = power * (path_length * path_length / (1.0 - specular));
+            return Some(Photon::new(comps.point, ray.direction, stored));
+        }
+    }
+    None
+}
+fn axis_value(t: Tuple, axis: usize) -> f32 {
+    match axis {
+        0 => t.x,
+        1 => t.y,
+        _ => t.z,
+    }
+}
+/// Arrange photons so the median of every slice splits it along x, y and z in turn
+fn build(photons: &mut [Photon], depth: usize) {
+    if photons.len() <= 1 {
+        return;
+    }
+    let axis = depth % 3;
+    let mid = photons.len() / 2;
+    photons.select_nth_unstable_by(mid, |a, b| {
+        axis_value(a.position, axis).total_cmp(&axis_value(b.position, axis))
+    });
+    let (left, right) = photons.split_at_mut(mid);
+    build(left, depth + 1);
+    build(&mut right[1..], depth + 1);
+}
+fn gather<'a>(
+    photons: &'a [Photon],
+    depth: usize,
+    point: Tuple,
+    radius2: f32,
+    found: &mut Vec<&'a Photon>,
+) {
+    if photons.is_empty() {
+        return;
+    }
+    let axis = depth % 3;
+    let mid = photons.len() / 2;
+    let photon = &photons[mid];
+    let offset = photon.position - point;
+    if offset.dot(offset) <= radius2 {
+        found.push(photon);
+    }
+    let split = axis_value(point, axis) - axis_value(photon.position, axis);
+    let (near, far) = if split < 0.0 {
+        (&photons[..mid], &photons[mid + 1..])
+    } else {
+        (&photons[mid + 1..], &photons[..mid])
+    };
+    gather(near, depth + 1, point, radius2, found);
+    if split * split <= radius2 {
+        gather(far, depth + 1, point, radius2, found);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use approx::assert_relative_eq;
+
+    use super::{Photon, PhotonMap};
+    use crate::{
+        colour::{Colour, WHITE},
+        ray::Light,
+        sampling::{random, seed},
+        shapes::Object,
+        transformation::translation,
+        tuple::{point, vector},
+        world::World,
+        DEFAULT_EPSILON,
+    };
+
+    #[test]
+    fn kd_tree_finds_same_photons_as_search() {
+        seed(40);
+        let photons: Vec<Photon> = (0..500)
+            .map(|_| {
+                Photon::new(
+                    point(random() * 10.0, random() * 10.0, random() * 10.0),
+                    vector(0.0, -1.0, 0.0),
+                    WHITE,
+                )
+            })
+            .collect();
+        let map = PhotonMap::new(photons.clone(), 1.0);
+        assert_eq!(map.len(), 500);
+        for _ in 0..20 {
+            let centre = point(random() * 10.0, random() * 10.0, random() * 10.0);
+            let mut expected: Vec<Photon> = photons
+                .iter()
+                .filter(|p| (p.position - centre).magnitude() <= 1.5)
+                .cloned()
+                .collect();
+            let mut found: Vec<Photon> =
+                map.photons_near(centre, 1.5).into_iter().cloned().collect();
+            let order = |a: &Photon, b: &Photon| a.position.x.total_cmp(&b.position.x);
+            expected.sort_by(order);
+            found.sort_by(order);
+            assert_eq!(found, expected);
+        }
+    }
+    #[test]
+    fn irradiance_from_photon_density() {
+        let photons = vec![
+            Photon::new(
+                point(0.0, 0.0, 0.0),
+                vector(0.0, -1.0, 0.0),
+                Colour::new(1.0, 0.5, 0.0),
+            ),
+            Photon::new(
+                point(0.5, 0.0, 0.0),
+                vector(0.0, -1.0, 0.0),
+                Colour::new(1.0, 0.5, 0.0),
+            ),
+            // Too far away
+            Photon::new(point(2.0, 0.0, 0.0), vector(0.0, -1.0, 0.0), WHITE),
+            // Arrived from behind the surface
+            Photon::new(point(0.0, 0.0, 0.5), vector(0.0, 1.0, 0.0), WHITE),
+        ];
+        let map = PhotonMap::new(photons, 1.0);
+        assert_relative_eq!(
+            map.irradiance(point(0.0, 0.0, 0.0), vector(0.0, 1.0, 0.0)),
+            Colour::new(2.0 / PI, 1.0 / PI, 0.0),
+            epsilon = DEFAULT_EPSILON
+        );
+    }
+    #[test]
+    fn mirrored_light_matches_direct_lighting() {
+        seed(40);
+        let mut world = World::new();
+        world.lights.push(Light::new(point(0.0, 1.0, 0.0), WHITE));
+        world.objects.push(Object::new_plane());
+        let mut mirror = Object::new_plane();
+        mirror.transform = translation(0.0, 2.0, 0.0);
+        mirror.material.reflective = 1.0;
+        world.objects.push(mirror);
+        // The floor sees the light's reflection straight above it, which lights it as fully as the light itself
+        let map = PhotonMap::trace(&world, 20000, 0.5);
+        let irradiance = map.irradiance(point(0.0, 0.0, 0.0), vector(0.0, 1.0, 0.0));
+        assert_relative_eq!(irradiance.red, 1.0, epsilon = 0.2);
+    }
+    #[test]
+    fn glass_sphere_focuses_light_onto_floor() {
+        seed(40);
+        let mut world = World::new();
+        world.lights.push(Light::new(point(0.0, 4.0, 0.0), WHITE));
+        world.objects.push(Object::new_plane());
+        let mut ball = Object::glass_sphere();
+        ball.transform = translation(0.0, 2.0, 0.0);
+        world.objects.push(ball);
+        let map = PhotonMap::trace(&world, 20000, 0.3);
+        assert!(!map.is_empty());
+        let normal = vector(0.0, 1.0, 0.0);
+        assert!(map.irradiance(point(0.0, 0.0, 0.0), normal).red > 1.0);
+        assert_eq!(
+            map.irradiance(point(4.0, 0.0, 0.0), normal),
+            Colour::new(0.0, 0.0, 0.0)
+        );
+    }
+}
@@ -0,0 +1,158 @@
+use std::f64::consts::PI;
+
+/// Coefficients smaller than this are treated as zero.
+const EPSILON: f64 = 1e-12;
+/// Newton iterations used to polish the roots of a quartic.
+const POLISH_STEPS: usize = 3;
+
+/// Real roots of a x^2 + b x + c = 0 in ascending order, using the form that avoids cancellation
+pub fn solve_quadratic(a: f64, b: f64, c: f64) -> Vec<f64> {
+    if a.abs() < EPSILON {
+        return if b.abs() < EPSILON {
+            vec![]
+        } else {
+            vec![-c / b]
+        };
+    }
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 {
+        return vec![];
+    }
+    let q = -0.5 * (b + b.signum() * discriminant.sqrt());
+    if q.abs() < EPSILON {
+        // b and c are both zero
+        return vec![0.0, 0.0];
+    }
+    let mut roots = vec![q / a, c / q];
+    roots.sort_by(f64::total_cmp);
+    roots
+}
+/// Real roots of a x^3 + b x^2 + c x + d = 0 in ascending order
+pub fn solve_cubic(a: f64, b: f64, c: f64, d: f64) -> Vec<f64> {
+    if a.abs() < EPSILON {
+        return solve_quadratic(b, c, d);
+    }
+    let (b, c, d) = (b / a, c / a, d / a);
+    // Substitute x = y - b/3 to get y^3 + p y + q = 0
+    let p = c - b * b / 3.0;
+    let q = 2.0 * b * b * b / 27.0 - b * c / 3.0 + d;
+    let shift = -b / 3.0;
+    let discriminant = q * q / 4.0 + p * p * p / 27.0;
+    let mut roots = if p.abs() < EPSILON && q.abs() < EPSILON {
+        vec![shift]
+    } else if discriminant > 0.0 {
+        let sqrt_d = discriminant.sqrt();
+        vec![(-q / 2.0 + sqrt_d).cbrt() + (-q / 2.0 - sqrt_d).cbrt() + shift]
+    } else {
+        // Three real roots, found with the trigonometric method
+        let r = (-p / 3.0).sqrt();
+        let phi = (-q / (2.0 * r * r * r)).clamp(-1.0, 1.0).acos();
+        (0..3)
+            .map(|k| 2.0 * r * ((phi + 2.0 * PI * k as f64) / 3.0).cos() + shift)
+            .collect()
+    };
+    roots.sort_by(f64::total_cmp);
+    roots
+}
+/// Real roots of a x^4 + b x^3 + c x^2 + d x + e = 0 in ascending order, found with Ferrari's method
+/// and polished with Newton's method
+pub fn solve_quartic(a: f64, b: f64, c: f64, d: f64, e: f64) -> Vec<f64> {
+    if a.abs() < EPSILON {
+        return solve_cubic(b, c, d, e);
+    }
+    let (b, c, d, e) = (b / a, c / a, d / a, e / a);
+    // Substitute x = y - b/4 to get y^4 + p y^2 + q y + r = 0
+    let b2 = b * b;
+    let p = c - 3.0 * b2 / 8.0;
+    let q = d - b * c / 2.0 + b2 * b / 8.0;
+    let r = e - b * d / 4.0 + b2 * c / 16.0 - 3.0 * b2 * b2 / 256.0;
+    let mut ys = Vec::new();
+    if q.abs() < EPSILON {
+        // A quadratic in y^2
+        for z in solve_quadratic(1.0, p, r) {
+            if z >= 0.0 {
+                ys.push(z.sqrt());
+                ys.push(-z.sqrt());
+            }
+        }
+    } else {
+        // Split into two quadratics using a positive root of the resolvent cubic
+        let m = solve_cubic(8.0, 8.0 * p, 2.0 * p * p - 8.0 * r, -q * q)
+            .into_iter()
+            .fold(f64::NEG_INFINITY, f64::max);
+        if m <= 0.0 {
+            return vec![];
+        }
+        let w = (2.0 * m).sqrt();
+        ys.extend(solve_quadratic(1.0, -w, p / 2.0 + m + q / (2.0 * w)));
+        ys.extend(solve_quadratic(1.0, w, p / 2.0 + m - q / (2.0 * w)));
+    }
+    let mut roots: Vec<f64> = ys
+        .into_iter()
+        .map(|y| polish(y - b / 4.0, [1.0, b, c, d, e]))
+        .collect();
+    roots.sort_by(f64::total_cmp);
+    roots
+}
+/// Improve a root of a quartic with a few Newton steps
+fn polish(mut x: f64, [a, b, c, d, e]: [f64; 5]) -> f64 {
+    for _ in 0..POLISH_STEPS {
+        let f = (((a * x + b) * x + c) * x + d) * x + e;
+        let df = ((4.0 * a * x + 3.0 * b) * x + 2.0 * c) * x + d;
+        if df.abs() < EPSILON {
+            break;
+        }
+        x -= f / df;
+    }
+    x
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+
+    use super::{solve_cubic, solve_quadratic, solve_quartic};
+
+    fn assert_roots(found: Vec<f64>, expected: &[f64]) {
+        assert_eq!(found.len(), expected.len(), "{:?}", found);
+        for (f, e) in found.iter().zip(expected) {
+            assert_relative_eq!(f, e, epsilon = 1e-9);
+        }
+    }
+    #[test]
+    fn quadratic_roots() {
+        assert_roots(solve_quadratic(1.0, -3.0, 2.0), &[1.0, 2.0]);
+        assert_roots(solve_quadratic(1.0, 0.0, 1.0), &[]);
+        assert_roots(solve_quadratic(0.0, 2.0, -4.0), &[2.0]);
+        // Widely separated roots keep their precision
+        assert_roots(solve_quadratic(1.0, -1e8, 1.0), &[1e-8, 1e8]);
+    }
+    #[test]
+    fn cubic_roots() {
+        // (x - 1)(x - 2)(x - 3)
+        assert_roots(solve_cubic(1.0, -6.0, 11.0, -6.0), &[1.0, 2.0, 3.0]);
+        // (x - 2)(x^2 + 1)
+        assert_roots(solve_cubic(1.0, -2.0, 1.0, -2.0), &[2.0]);
+        assert_roots(solve_cubic(2.0, 0.0, 0.0, -16.0), &[2.0]);
+    }
+    #[test]
+    fn quartic_roots() {
+        // (x - 1)(x - 2)(x - 3)(x - 4)
+        assert_roots(
+            solve_quartic(1.0, -10.0, 35.0, -50.0, 24.0),
+            &[1.0, 2.0, 3.0, 4.0],
+        );
+        // (x^2 - 4)(x^2 - 9), no odd terms
+        assert_roots(
+            solve_quartic(1.0, 0.0, -13.0, 0.0, 36.0),
+            &[-3.0, -2.0, 2.0, 3.0],
+        );
+        // (x^2 + 1)(x^2 + 4)
+        assert_roots(solve_quartic(1.0, 0.0, 5.0, 0.0, 4.0), &[]);
+        // (x - 0.5)(x + 0.25)(x^2 + 1), scaled
+        assert_roots(
+            solve_quartic(3.0, -0.75, 2.625, -0.75, -0.375),
+            &[-0.25, 0.5],
+        );
+    }
+}
@@ -0,0 +1,219 @@
+use std::sync::Arc;
+
+use crate::{
+    colour::{Colour, BLACK},
+    tuple::Tuple,
+};
+
+/// A colour at a position along a colour ramp.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct ColourStop {
+    pub position: f32,
+    pub colour: Colour,
+}
+impl ColourStop {
+    /// Create a new colour stop
+    pub fn new(position: f32, colour: Colour) -> ColourStop {
+        ColourStop { position, colour }
+    }
+}
+/// How a point in pattern space is turned into a position along the ramp.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum RampShape {
+    /// The x coordinate.
+    Linear,
+    /// The distance from the y axis.
+    Radial,
+    /// The distance from the origin.
+    Spherical,
+}
+/// What happens to positions before the first stop or after the last stop.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum RampExtend {
+    /// Keep the colour of the nearest end stop.
+    Clamp,
+    /// Start again from the first stop.
+    Repeat,
+    /// Run back and forth between the end stops.
+    Mirror,
+}
+/// How colours are blended between neighbouring stops.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum RampInterpolation {
+    Linear,
+    /// Ease in and out of each stop.
+    Smoothstep,
+}
+/// A gradient made of any number of colour stops.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ColourRamp {
+    /// The stops, sorted by position.
+    stops: Arc<Vec<ColourStop>>,
+    pub shape: RampShape,
+    pub extend: RampExtend,
+    pub interpolation: RampInterpolation,
+}
+impl ColourRamp {
+    /// Create a new linear, clamped ramp. The stops are sorted by position.
+    pub fn new(mut stops: Vec<ColourStop>) -> ColourRamp {
+        stops.sort_by(|a, b| a.position.total_cmp(&b.position));
+        ColourRamp {
+            stops: Arc::new(stops),
+            shape: RampShape::Linear,
+            extend: RampExtend::Clamp,
+            interpolation: RampInterpolation::Linear,
+        }
+    }
+    /// Return the stops sorted by position
+    pub fn stops(&self) -> &[ColourStop] {
+        &self.stops
+    }
+    /// Find the colour of the ramp at a point in pattern space
+    pub fn colour_at_point(&self, point: Tuple) -> Colour {
+        let position = match self.shape {
+            RampShape::Linear => point.x,
+            RampShape::Radial => (point.x.powi(2) + point.z.powi(2)).sqrt(),
+            RampShape::Spherical => (point.x.powi(2) + point.y.powi(2) + point.z.powi(2)).sqrt(),
+        };
+        self.colour_at(position)
+    }
+    /// Find the colour of the ramp at a position
+    pub fn colour_at(&self, position: f32) -> Colour {
+        let (first, last) = match (self.stops.first(), self.stops.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return BLACK,
+        };
+        let length = last.position - first.position;
+        if length <= 0.0 {
+            return first.colour;
+        }
+        let offset = position - first.position;
+        let position = match self.extend {
+            RampExtend::Clamp => position.clamp(first.position, last.position),
+            RampExtend::Repeat => first.position + offset.rem_euclid(length),
+            RampExtend::Mirror => {
+                let folded = offset.rem_euclid(2.0 * length);
+                first.position
+                    + if folded > length {
+                        2.0 * length - folded
+                    } else {
+                        folded
+                    }
+            }
+        };
+        let next = self
+            .stops
+            .iter()
+            .position(|stop| stop.position > position)
+            .unwrap_or(self.stops.len() - 1)
+            .max(1);
+        let a = self.stops[next - 1];
+        let b = self.stops[next];
+        if b.position <= a.position {
+            return b.colour;
+        }
+        let mut fraction = ((position - a.position) / (b.position - a.position)).clamp(0.0, 1.0);
+        if self.interpolation == RampInterpolation::Smoothstep {
+            fraction = fraction * fraction * (3.0 - 2.0 * fraction);
+        }
+        a.colour + (b.colour - a.colour) * fraction
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+
+    use super::{ColourRamp, ColourStop, RampExtend, RampInterpolation, RampShape};
+    use crate::{
+        colour::{Colour, BLACK, BLUE, GREEN, RED, WHITE},
+        shapes::Pattern,
+        tuple::point,
+        DEFAULT_EPSILON,
+    };
+
+    fn rgb_ramp() -> ColourRamp {
+        ColourRamp::new(vec![
+            ColourStop::new(1.0, BLUE),
+            ColourStop::new(0.0, RED),
+            ColourStop::new(0.5, GREEN),
+        ])
+    }
+    #[test]
+    fn stops_are_sorted() {
+        let ramp = rgb_ramp();
+        let positions: Vec<f32> = ramp.stops().iter().map(|s| s.position).collect();
+        assert_eq!(positions, vec![0.0, 0.5, 1.0]);
+    }
+    #[test]
+    fn ramp_interpolates_between_stops() {
+        let ramp = rgb_ramp();
+        assert_eq!(ramp.colour_at(0.0), RED);
+        assert_eq!(ramp.colour_at(0.25), Colour::new(0.5, 0.5, 0.0));
+        assert_eq!(ramp.colour_at(0.5), GREEN);
+        assert_eq!(ramp.colour_at(0.75), Colour::new(0.0, 0.5, 0.5));
+        assert_eq!(ramp.colour_at(1.0), BLUE);
+    }
+    #[test]
+    fn ramp_extension_modes() {
+        let mut ramp = rgb_ramp();
+        assert_eq!(ramp.colour_at(-1.0), RED);
+        assert_eq!(ramp.colour_at(2.5), BLUE);
+        ramp.extend = RampExtend::Repeat;
+        assert_eq!(ramp.colour_at(1.25), Colour::new(0.5, 0.5, 0.0));
+        assert_eq!(ramp.colour_at(-0.5), GREEN);
+        ramp.extend = RampExtend::Mirror;
+        assert_eq!(ramp.colour_at(1.25), Colour::new(0.0, 0.5, 0.5));
+        assert_eq!(ramp.colour_at(-0.25), Colour::new(0.5, 0.5, 0.0));
+        assert_eq!(ramp.colour_at(2.0), RED);
+    }
+    #[test]
+    fn ramp_smoothstep() {
+        let mut ramp = ColourRamp::new(vec![
+            ColourStop::new(0.0, BLACK),
+            ColourStop::new(1.0, WHITE),
+        ]);
+        ramp.interpolation = RampInterpolation::Smoothstep;
+        assert_relative_eq!(
+            ramp.colour_at(0.25),
+            Colour::new(0.15625, 0.15625, 0.15625),
+            epsilon = DEFAULT_EPSILON
+        );
+        assert_eq!(ramp.colour_at(0.5), Colour::new(0.5, 0.5, 0.5));
+    }
+    #[test]
+    fn ramp_with_few_stops() {
+        assert_eq!(ColourRamp::new(vec![]).colour_at(0.3), BLACK);
+        assert_eq!(
+            ColourRamp::new(vec![ColourStop::new(0.5, RED)]).colour_at(0.3),
+            RED
+        );
+    }
+    #[test]
+    fn ramp_shapes() {
+        let mut ramp = ColourRamp::new(vec![
+            ColourStop::new(0.0, BLACK),
+            ColourStop::new(2.0, WHITE),
+        ]);
+        assert_eq!(
+            ramp.colour_at_point(point(1.0, 5.0, 5.0)),
+            Colour::new(0.5, 0.5, 0.5)
+        );
+        ramp.shape = RampShape::Radial;
+        assert_eq!(
+            ramp.colour_at_point(point(0.6, 5.0, 0.8)),
+            Colour::new(0.5, 0.5, 0.5)
+        );
+        ramp.shape = RampShape::Spherical;
+        assert_eq!(
+            ramp.colour_at_point(point(0.0, 0.6, 0.8)),
+            Colour::new(0.5, 0.5, 0.5)
+        );
+    }
+    #[test]
+    fn ramp_pattern() {
+        let p = Pattern::new_ramp(rgb_ramp());
+        assert_eq!(p.pattern_at(point(0.5, 0.0, 0.0)), GREEN);
+        assert_eq!(p.pattern_at(point(3.0, 0.0, 0.0)), BLUE);
+    }
+}
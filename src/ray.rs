@@ -1,6 +1,7 @@
 use crate::{
     colour::{self, Colour},
     matrix::Matrix4x4,
+    polynomial,
     shapes::{self, Object, Shape},
     tuple::{self, point, vector, Tuple},
     DEFAULT_EPSILON,
@@ -8,7 +9,7 @@ use crate::{
 use std::cmp::Ordering;
 
 /// A ray.
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub struct Ray {
     pub origin: tuple::Tuple,
     pub direction: tuple::Tuple,
@@ -25,7 +26,7 @@ impl Ray {
     /// Calculate the intersections between the ray and the given shape.
     pub fn intersect(&self, object: &shapes::Object) -> Vec<Intersection> {
         let transformed_ray = self.transform(object.transform.inverse());
-        match object.shape {
+        match &object.shape {
             Shape::Sphere() => {
                 let origin_to_center = transformed_ray.origin - point(0.0, 0.0, 0.0);
                 let a = transformed_ray.direction.dot(transformed_ray.direction);
@@ -38,21 +39,50 @@ impl Ray {
                 let t1 = (-b - discriminant.sqrt()) / (2.0 * a);
                 let t2 = (-b + discriminant.sqrt()) / (2.0 * a);
                 vec![
-                    Intersection::new(t1, *object),
-                    Intersection::new(t2, *object),
+                    Intersection::new(t1, object.clone()),
+                    Intersection::new(t2, object.clone()),
                 ]
             }
             Shape::Test() => {
                 vec![]
             }
-            Shape::Plane() => {
-                if self.direction.y.abs() < DEFAULT_EPSILON {
-                    vec![]
-                } else {
-                    let t = (-transformed_ray.origin.y) / transformed_ray.direction.y;
-                    vec![Intersection::new(t, *object)]
-                }
+            Shape::Plane() => intersect_xz(&transformed_ray, |_, _| true)
+                .map(|t| Intersection::new(t, object.clone()))
+                .into_iter()
+                .collect(),
+            Shape::Disc { radius } => {
+                intersect_xz(&transformed_ray, |x, z| x * x + z * z <= radius * radius)
+                    .map(|t| Intersection::new(t, object.clone()))
+                    .into_iter()
+                    .collect()
             }
+            Shape::Annulus { inner, outer } => intersect_xz(&transformed_ray, |x, z| {
+                let d2 = x * x + z * z;
+                d2 >= inner * inner && d2 <= outer * outer
+            })
+            .map(|t| Intersection::new(t, object.clone()))
+            .into_iter()
+            .collect(),
+            Shape::Rectangle { width, depth } => intersect_xz(&transformed_ray, |x, z| {
+                x.abs() <= width / 2.0 && z.abs() <= depth / 2.0
+            })
+            .map(|t| Intersection::new(t, object.clone()))
+            .into_iter()
+            .collect(),
+            Shape::Torus { major, minor } => intersect_torus(&transformed_ray, *major, *minor)
+                .into_iter()
+                .map(|t| Intersection::new(t, object.clone()))
+                .collect(),
+            Shape::Heightfield(heightfield) => heightfield
+                .intersect(transformed_ray.origin, transformed_ray.direction)
+                .into_iter()
+                .map(|t| Intersection::new(t, object.clone()))
+                .collect(),
+            Shape::Sdf(sdf) => sdf
+                .march(transformed_ray.origin, transformed_ray.direction)
+                .into_iter()
+                .map(|t| Intersection::new(t, object.clone()))
+                .collect(),
         }
     }
     /// Transform the ray by a 4x4 matrix.
@@ -64,48 +94,52 @@ impl Ray {
     pub fn prepare_computations(self, inter: &Intersection, inters: Intersections) -> Computations {
         let point = self.position(inter.t);
         let eyev = -(self.direction);
-        let inside;
-        let mut normalv = inter.object.normal_at(point);
-        if normalv.dot(eyev) < 0.0 {
-            inside = true;
+        let mut geometric_normalv = inter.object.normal_at(point);
+        let mut normalv = match inter.object.material.bump {
+            Some(_) => inter.object.shading_normal_at(point),
+            None => geometric_normalv,
+        };
+        let inside = geometric_normalv.dot(eyev) < 0.0;
+        if inside {
+            geometric_normalv = vector(0.0, 0.0, 0.0) - geometric_normalv;
             normalv = vector(0.0, 0.0, 0.0) - normalv;
-        } else {
-            inside = false;
-            normalv = normalv;
         }
-        let over_point = point + normalv * (DEFAULT_EPSILON); // TODO can  I reduce this factor and still stop the acne?
-        let under_point = point - normalv * (DEFAULT_EPSILON); // TODO can  I reduce this factor and still stop the acne?
+        // Offset along the geometric normal, a bumped normal could push the point back through the surface
+        let over_point = point + geometric_normalv * (DEFAULT_EPSILON); // TODO can  I reduce this factor and still stop the acne?
+        let under_point = point - geometric_normalv * (DEFAULT_EPSILON); // TODO can  I reduce this factor and still stop the acne?
         let reflectv = self.direction.reflect(normalv);
         let mut n1 = 1.0;
         let mut n2 = 1.0;
-        let mut containers: Vec<Object> = Vec::new();
+        let mut containers: Vec<Intersection> = Vec::new();
         for x in inters.inters {
-            if x == *inter {
-                if let Some(o) = containers.last() {                    
-                    n1 = o.material.refractive_index;
+            let is_hit = x == *inter;
+            if is_hit {
+                if let Some(o) = containers.last() {
+                    n1 = o.object.material.refractive_index;
                 } else {
                     n1 = 1.0;
                 }
             }
-            if let Some(index) = containers.iter().position(|value| *value == x.object) {
+            if let Some(index) = containers.iter().position(|value| value.same_object(&x)) {
                 //remove object from container if it already exists as we're exiting the object
                 containers.remove(index);
             } else {
                 //add object to container if it doesn't exist as we're entering the object
-                containers.push(x.object);
+                containers.push(x);
             }
-            if x == *inter {
-                if let Some(o) = containers.last() {                    
-                    n2 = o.material.refractive_index;
+            if is_hit {
+                if let Some(o) = containers.last() {
+                    n2 = o.object.material.refractive_index;
                 } else {
                     n2 = 1.0;
                 }
-                break;                
+                break;
             }
         }
         Computations {
             t: inter.t,
-            object: inter.object,
+            object: inter.object.clone(),
+            index: inter.index,
             point,
             normalv,
             eyev,
@@ -114,13 +148,62 @@ impl Ray {
             under_point,
             reflectv,
             n1,
-            n2
+            n2,
         }
     }
 }
+/// The t where a ray crosses the xz-plane, if it does so at an x and z accepted by inside
+fn intersect_xz(ray: &Ray, inside: impl Fn(f32, f32) -> bool) -> Option<f32> {
+    if ray.direction.y.abs() < DEFAULT_EPSILON {
+        return None;
+    }
+    let t = -ray.origin.y / ray.direction.y;
+    let p = ray.position(t);
+    if inside(p.x, p.z) {
+        Some(t)
+    } else {
+        None
+    }
+}
+/// Find where a ray in object space crosses a torus around the y axis.
+/// The quartic is solved in f64 along the unit direction from the point of the ray closest to the origin,
+/// which keeps the coefficients small for rays that start far away.
+fn intersect_torus(ray: &Ray, major: f32, minor: f32) -> Vec<f32> {
+    let speed = ray.direction.magnitude() as f64;
+    let (dx, dy, dz) = (
+        ray.direction.x as f64 / speed,
+        ray.direction.y as f64 / speed,
+        ray.direction.z as f64 / speed,
+    );
+    let (ox, oy, oz) = (
+        ray.origin.x as f64,
+        ray.origin.y as f64,
+        ray.origin.z as f64,
+    );
+    let start = -(ox * dx + oy * dy + oz * dz);
+    let (ox, oy, oz) = (ox + dx * start, oy + dy * start, oz + dz * start);
+    let (major, minor) = (major as f64, minor as f64);
+    let r2 = 4.0 * major * major;
+    let b = ox * dx + oy * dy + oz * dz;
+    let g = ox * ox + oy * oy + oz * oz + major * major - minor * minor;
+    let roots = polynomial::solve_quartic(
+        1.0,
+        4.0 * b,
+        4.0 * b * b + 2.0 * g - r2 * (dx * dx + dz * dz),
+        4.0 * b * g - 2.0 * r2 * (ox * dx + oz * dz),
+        g * g - r2 * (ox * ox + oz * oz),
+    );
+    roots
+        .into_iter()
+        .map(|s| ((s + start) / speed) as f32)
+        .collect()
+}
+#[derive(Clone)]
 pub struct Computations {
     pub t: f32,
     pub object: Object,
+    /// Index of the object in `World::objects`, None when the object was intersected on its own.
+    pub index: Option<usize>,
     pub point: Tuple,
     pub normalv: Tuple,
     pub eyev: Tuple,
@@ -138,24 +221,37 @@ pub fn schlick(comps: Computations) -> f32 {
         let n = comps.n1 / comps.n2;
         let sin2_t = f32::powi(n, 2) * (1.0 - f32::powi(cos, 2));
         if sin2_t > 1.0 {
-            return 1.0
+            return 1.0;
         }
         let cos_t = f32::sqrt(1.0 - sin2_t);
         cos = cos_t;
     }
-    let r0 = f32::powi((comps.n1 - comps.n2)/(comps.n1 + comps.n2), 2);
+    let r0 = f32::powi((comps.n1 - comps.n2) / (comps.n1 + comps.n2), 2);
     r0 + (1.0 - r0) * f32::powi(1.0 - cos, 5)
-} 
+}
 /// An intersection between a ray and a shape.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Debug)]
 pub struct Intersection {
     pub t: f32,
     pub object: Object,
+    /// Index of the object in `World::objects`, None when the object was intersected on its own.
+    pub index: Option<usize>,
 }
 impl Intersection {
     /// Create a new intersection with the given t and shape.
     pub fn new(t: f32, object: Object) -> Intersection {
-        Intersection { t, object }
+        Intersection {
+            t,
+            object,
+            index: None,
+        }
+    }
+    /// Check if two intersections are with the same object, by index when both come from a world
+    pub fn same_object(&self, other: &Intersection) -> bool {
+        match (self.index, other.index) {
+            (Some(a), Some(b)) => a == b,
+            _ => self.object == other.object,
+        }
     }
 }
 impl Ord for Intersection {
@@ -176,7 +272,7 @@ impl PartialEq for Intersection {
 impl Eq for Intersection {}
 
 /// A collection of intersections.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Intersections {
     pub inters: Vec<Intersection>,
 }
@@ -189,14 +285,7 @@ impl Intersections {
     }
     /// Return the closest intersection that is not behind the ray.
     pub fn hit(&self) -> Option<Intersection> {
-        let mut hit = None;
-        for x in self.inters.clone() {
-            if x.t > 0.0 {
-                hit = Some(x);
-                break;
-            }
-        }
-        hit
+        self.inters.iter().find(|x| x.t > 0.0).cloned()
     }
 }
 #[derive(Clone, Copy, PartialEq, Debug)]
@@ -213,54 +302,73 @@ impl Light {
         }
     }
 }
+/// Phong shade a point, light_transmittance is how much of each channel of the light gets past shadowing objects
 pub fn lighting(
-    object: Object,
+    object: &Object,
+    light: Light,
+    point: Tuple,
+    eyev: Tuple,
+    normalv: Tuple,
+    light_transmittance: Colour,
+) -> Colour {
+    let ambient = ambient_lighting(object, light, point);
+    if light_transmittance == colour::BLACK {
+        return ambient;
+    }
+    ambient + direct_lighting(object, light, point, eyev, normalv) * light_transmittance
+}
+/// The ambient term of the Phong shading of a point lit by light
+pub fn ambient_lighting(object: &Object, light: Light, point: Tuple) -> Colour {
+    object.pattern_at(point) * light.intensity * object.material.ambient
+}
+/// The diffuse and specular light reaching the eye straight from light, ignoring shadows and the ambient term
+pub fn direct_lighting(
+    object: &Object,
     light: Light,
     point: Tuple,
     eyev: Tuple,
     normalv: Tuple,
-    in_shadow: bool,
 ) -> Colour {
     let pattern_colour = object.pattern_at(point);
     let effective_colour = pattern_colour * light.intensity;
-    let ambient = effective_colour * object.material.ambient;
     let lightv = (light.position - point).normalize();
+    if let Some(pbr) = &object.material.pbr {
+        return pbr.direct(pattern_colour, light.intensity, lightv, eyev, normalv);
+    }
     let light_dot_normal = lightv.dot(normalv);
-    let diffuse;
-    let specular;
-    if in_shadow {
-        specular = colour::BLACK;
-        diffuse = colour::BLACK;
-    } else if light_dot_normal < 0.0 {
-        diffuse = colour::BLACK;
-        specular = colour::BLACK;
+    if light_dot_normal < 0.0 {
+        return colour::BLACK;
+    }
+    let diffuse = effective_colour * object.material.diffuse * light_dot_normal;
+    let reflectv = (-lightv).reflect(normalv);
+    let reflect_dot_eye = reflectv.dot(eyev);
+    let specular = if reflect_dot_eye <= 0.0 {
+        colour::BLACK
     } else {
-        diffuse = effective_colour * object.material.diffuse * lightv.dot(normalv);
-        let reflectv = (-lightv).reflect(normalv);
-        let reflect_dot_eye = reflectv.dot(eyev);
-        if reflect_dot_eye <= 0.0 {
-            specular = colour::BLACK;
-        } else {
-            let factor = f32::powf(reflect_dot_eye, object.material.shininess);
-            specular = light.intensity * object.material.specular * factor;
-        }
-    }
-    ambient + diffuse + specular
+        let factor = f32::powf(reflect_dot_eye, object.material.shininess);
+        light.intensity * object.material.specular * factor
+    };
+    diffuse + specular
 }
 #[cfg(test)]
 mod tests {
     use super::Intersection;
     use crate::{
         colour::{self, Colour, BLACK, WHITE},
+        heightfield::Heightfield,
         matrix,
         ray::{lighting, schlick, Intersections, Light, Ray},
+        sdf::Sdf,
         shapes::{Material, Object, Pattern},
-        transformation::{rot_z, scale, translation},
+        transformation::{rot_x, rot_z, scale, translation},
         tuple::{point, vector},
         DEFAULT_EPSILON,
     };
     use approx::assert_relative_eq;
-    use std::{f32::consts::PI, vec};
+    use std::{
+        f32::consts::{FRAC_1_SQRT_2, PI},
+        vec,
+    };
 
     #[test]
     fn create_ray() {
@@ -324,15 +432,15 @@ mod tests {
     #[test]
     fn intersection_encapsulates() {
         let s = Object::new_sphere();
-        let i = Intersection::new(3.5, s);
+        let i = Intersection::new(3.5, s.clone());
         assert_eq!(i.t, 3.5);
         assert_eq!(i.object, s);
     }
     #[test]
     fn aggregating_intersection() {
         let s = Object::new_sphere();
-        let i1 = Intersection::new(1.0, s);
-        let i2 = Intersection::new(2.0, s);
+        let i1 = Intersection::new(1.0, s.clone());
+        let i2 = Intersection::new(2.0, s.clone());
         let intersections = Intersections::new(vec![i1, i2]);
         assert_eq!(intersections.inters.len(), 2);
         assert_eq!(intersections.inters[0].t, 1.0);
@@ -350,35 +458,35 @@ mod tests {
     #[test]
     fn hit_positive_t() {
         let s = Object::new_sphere();
-        let i1 = Intersection::new(1.0, s);
-        let i2 = Intersection::new(2.0, s);
-        let intersections = Intersections::new(vec![i2, i1]);
+        let i1 = Intersection::new(1.0, s.clone());
+        let i2 = Intersection::new(2.0, s.clone());
+        let intersections = Intersections::new(vec![i2, i1.clone()]);
         assert_eq!(intersections.hit().unwrap(), i1);
     }
     #[test]
     fn hit_some_negative_t() {
         let s = Object::new_sphere();
-        let i1 = Intersection::new(-1.0, s);
-        let i2 = Intersection::new(2.0, s);
-        let intersections = Intersections::new(vec![i2, i1]);
+        let i1 = Intersection::new(-1.0, s.clone());
+        let i2 = Intersection::new(2.0, s.clone());
+        let intersections = Intersections::new(vec![i2.clone(), i1]);
         assert_eq!(intersections.hit().unwrap(), i2);
     }
     #[test]
     fn hit_all_negative_t() {
         let s = Object::new_sphere();
-        let i1 = Intersection::new(-2.0, s);
-        let i2 = Intersection::new(-1.0, s);
+        let i1 = Intersection::new(-2.0, s.clone());
+        let i2 = Intersection::new(-1.0, s.clone());
         let intersections = Intersections::new(vec![i2, i1]);
         assert_eq!(intersections.hit(), None);
     }
     #[test]
     fn hit_is_lowest_nonnegative() {
         let s = Object::new_sphere();
-        let i1 = Intersection::new(5.0, s);
-        let i2 = Intersection::new(7.0, s);
-        let i3 = Intersection::new(-3.0, s);
-        let i4 = Intersection::new(2.0, s);
-        let intersections = Intersections::new(vec![i2, i1, i3, i4]);
+        let i1 = Intersection::new(5.0, s.clone());
+        let i2 = Intersection::new(7.0, s.clone());
+        let i3 = Intersection::new(-3.0, s.clone());
+        let i4 = Intersection::new(2.0, s.clone());
+        let intersections = Intersections::new(vec![i2, i1, i3, i4.clone()]);
         assert_eq!(intersections.hit().unwrap(), i4);
     }
     #[test]
@@ -480,8 +588,12 @@ mod tests {
         let mut s = Object::new_sphere();
         let t = translation(0.0, 1.0, 0.0);
         s.transform = t;
-        let n = s.normal_at(point(0.0, 1.70711, -0.70711));
-        assert_relative_eq!(n, vector(0.0, 0.70711, -0.70711), epsilon = DEFAULT_EPSILON);
+        let n = s.normal_at(point(0.0, 1.0 + FRAC_1_SQRT_2, -FRAC_1_SQRT_2));
+        assert_relative_eq!(
+            n,
+            vector(0.0, FRAC_1_SQRT_2, -FRAC_1_SQRT_2),
+            epsilon = DEFAULT_EPSILON
+        );
     }
     #[test]
     fn normal_transformed() {
@@ -529,7 +641,7 @@ mod tests {
         let eyev = vector(0.0, 0.0, -1.0);
         let normalv = vector(0.0, 0.0, -1.0);
         let light = Light::new(point(0.0, 0.0, -10.0), colour::WHITE);
-        let result = lighting(o, light, p, eyev, normalv, false);
+        let result = lighting(&o, light, p, eyev, normalv, colour::WHITE);
         assert_eq!(result, Colour::new(1.9, 1.9, 1.9));
     }
     #[test]
@@ -544,7 +656,7 @@ mod tests {
         let eyev = vector(0.0, 0.0, -1.0);
         let normalv = vector(0.0, 0.0, -1.0);
         let light = Light::new(point(0.0, 0.0, -10.0), colour::WHITE);
-        let result = lighting(o, light, p, eyev, normalv, false);
+        let result = lighting(&o, light, p, eyev, normalv, colour::WHITE);
         assert_eq!(result, Colour::new(0.5, 0.5, 0.5));
     }
     #[test]
@@ -554,7 +666,7 @@ mod tests {
         let eyev = vector(0.0, f32::sqrt(2.0) / 2.0, -f32::sqrt(2.0) / 2.0);
         let normalv = vector(0.0, 0.0, -1.0);
         let light = Light::new(point(0.0, 0.0, -10.0), colour::WHITE);
-        let result = lighting(o, light, p, eyev, normalv, false);
+        let result = lighting(&o, light, p, eyev, normalv, colour::WHITE);
         assert_eq!(result, Colour::new(1.0, 1.0, 1.0));
     }
     #[test]
@@ -564,7 +676,7 @@ mod tests {
         let eyev = vector(0.0, 0.0, -1.0);
         let normalv = vector(0.0, 0.0, -1.0);
         let light = Light::new(point(0.0, 10.0, -10.0), colour::WHITE);
-        let result = lighting(o, light, p, eyev, normalv, false);
+        let result = lighting(&o, light, p, eyev, normalv, colour::WHITE);
         assert_relative_eq!(
             result,
             Colour::new(0.7364, 0.7364, 0.7364),
@@ -578,7 +690,7 @@ mod tests {
         let eyev = vector(0.0, -f32::sqrt(2.0) / 2.0, -f32::sqrt(2.0) / 2.0);
         let normalv = vector(0.0, 0.0, -1.0);
         let light = Light::new(point(0.0, 10.0, -10.0), colour::WHITE);
-        let result = lighting(o, light, p, eyev, normalv, false);
+        let result = lighting(&o, light, p, eyev, normalv, colour::WHITE);
         assert_relative_eq!(
             result,
             Colour::new(1.63638, 1.63638, 1.63638),
@@ -592,15 +704,15 @@ mod tests {
         let eyev = vector(0.0, 0.0, -1.0);
         let normalv = vector(0.0, 0.0, -1.0);
         let light = Light::new(point(0.0, 0.0, 10.0), colour::WHITE);
-        let result = lighting(o, light, p, eyev, normalv, false);
+        let result = lighting(&o, light, p, eyev, normalv, colour::WHITE);
         assert_eq!(result, Colour::new(0.1, 0.1, 0.1));
     }
     #[test]
     fn prepare_comps() {
         let r = Ray::new(point(0., 0., -5.), vector(0., 0., 1.));
         let s = Object::new_sphere();
-        let i = Intersection::new(4., s);
-        let comps = r.prepare_computations(&i, Intersections::new(vec![i]));
+        let i = Intersection::new(4., s.clone());
+        let comps = r.prepare_computations(&i, Intersections::new(vec![i.clone()]));
         assert_eq!(comps.t, i.t);
         assert_eq!(comps.object, i.object);
         assert_eq!(comps.point, point(0.0, 0.0, -1.0));
@@ -611,16 +723,16 @@ mod tests {
     fn prepare_compus_hit_from_outside() {
         let r = Ray::new(point(0., 0., -5.), vector(0., 0., 1.));
         let s = Object::new_sphere();
-        let i = Intersection::new(4., s);
-        let comps = r.prepare_computations(&i, Intersections::new(vec![i]));
+        let i = Intersection::new(4., s.clone());
+        let comps = r.prepare_computations(&i, Intersections::new(vec![i.clone()]));
         assert!(!comps.inside);
     }
     #[test]
     fn prepare_compus_hit_from_inside() {
         let r = Ray::new(point(0., 0., 0.), vector(0., 0., 1.));
         let s = Object::new_sphere();
-        let i = Intersection::new(1., s);
-        let comps = r.prepare_computations(&i, Intersections::new(vec![i]));
+        let i = Intersection::new(1., s.clone());
+        let comps = r.prepare_computations(&i, Intersections::new(vec![i.clone()]));
         assert_eq!(comps.point, point(0.0, 0.0, 1.0));
         assert_eq!(comps.eyev, vector(0.0, 0.0, -1.0));
         assert_eq!(comps.normalv, vector(0.0, 0.0, -1.0));
@@ -631,14 +743,14 @@ mod tests {
         let eyev = vector(0.0, 0.0, -1.0);
         let normalv = vector(0.0, 0.0, -1.0);
         let l = Light::new(point(0.0, 0.0, -10.0), colour::WHITE);
-        let in_shadow = true;
+        let light_transmittance = colour::BLACK;
         let result = lighting(
-            Object::new(),
+            &Object::new(),
             l,
             point(0.0, 0.0, 0.0),
             eyev,
             normalv,
-            in_shadow,
+            light_transmittance,
         );
         assert_eq!(result, Colour::new(0.1, 0.1, 0.1));
     }
@@ -647,8 +759,8 @@ mod tests {
         let r = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
         let mut s = Object::new_sphere();
         s.transform = translation(0.0, 0.0, 1.0);
-        let i = Intersection::new(5.0, s);
-        let comps = r.prepare_computations(&i, Intersections::new(vec![i]));
+        let i = Intersection::new(5.0, s.clone());
+        let comps = r.prepare_computations(&i, Intersections::new(vec![i.clone()]));
         assert!(comps.point.z > comps.over_point.z);
     }
     #[test]
@@ -685,6 +797,47 @@ mod tests {
         assert_eq!(xs[0].object, p);
     }
     #[test]
+    fn intersect_rotated_plane_edge_on() {
+        // Parallel in world space is not parallel in object space once the plane is rotated
+        let mut p = Object::new_plane();
+        p.transform = rot_x(PI / 2.0);
+        let r = Ray::new(point(0.0, 0.0, -2.0), vector(0.0, 0.0, 1.0));
+        let xs = r.intersect(&p);
+        assert_eq!(xs.len(), 1);
+        assert_relative_eq!(xs[0].t, 2.0, epsilon = DEFAULT_EPSILON);
+    }
+    #[test]
+    fn intersect_bounded_flat_shapes() {
+        let down = |x: f32, z: f32| Ray::new(point(x, 1.0, z), vector(0.0, -1.0, 0.0));
+        let disc = Object::new_disc(1.0);
+        assert_eq!(down(0.5, 0.5).intersect(&disc).len(), 1);
+        assert_eq!(down(0.8, 0.8).intersect(&disc).len(), 0);
+        let ring = Object::new_annulus(0.5, 1.0);
+        assert_eq!(down(0.0, 0.0).intersect(&ring).len(), 0);
+        assert_eq!(down(0.0, -0.75).intersect(&ring).len(), 1);
+        assert_eq!(down(0.0, 1.5).intersect(&ring).len(), 0);
+        let mut rect = Object::new_rectangle(4.0, 2.0);
+        rect.transform = translation(0.0, -1.0, 0.0);
+        let xs = down(1.9, -0.9).intersect(&rect);
+        assert_eq!(xs.len(), 1);
+        assert_eq!(xs[0].t, 2.0);
+        assert_eq!(down(1.9, 1.1).intersect(&rect).len(), 0);
+        assert_eq!(rect.normal_at(point(0.0, -1.0, 0.0)), vector(0.0, 1.0, 0.0));
+    }
+    #[test]
+    fn uv_on_bounded_flat_shapes() {
+        let mut rect = Object::new_rectangle(4.0, 2.0);
+        rect.transform = translation(0.0, 0.0, 5.0) * rot_x(PI / 2.0);
+        let (u, v) = rect.uv_at(point(1.0, 0.5, 5.0)).unwrap();
+        assert_relative_eq!(u, 0.75, epsilon = DEFAULT_EPSILON);
+        assert_relative_eq!(v, 0.25, epsilon = DEFAULT_EPSILON);
+        assert_eq!(
+            Object::new_disc(1.0).uv_at(point(0.0, 0.0, 0.0)),
+            Some((0.5, 0.5))
+        );
+        assert_eq!(Object::new_plane().uv_at(point(0.0, 0.0, 0.0)), None);
+    }
+    #[test]
     fn lighting_with_pattern() {
         let mut m = Material::new();
         m.pattern = Some(Pattern::new_striped(WHITE, BLACK));
@@ -696,18 +849,38 @@ mod tests {
         let eyev = vector(0.0, 0.0, -1.0);
         let normalv = vector(0.0, 0.0, -1.0);
         let light = Light::new(point(0.0, 0.0, -10.0), WHITE);
-        let c1 = lighting(o, light, point(0.9, 0.0, 0.0), eyev, normalv, false);
-        let c2 = lighting(o, light, point(1.1, 0.0, 0.0), eyev, normalv, false);
+        let c1 = lighting(
+            &o,
+            light,
+            point(0.9, 0.0, 0.0),
+            eyev,
+            normalv,
+            colour::WHITE,
+        );
+        let c2 = lighting(
+            &o,
+            light,
+            point(1.1, 0.0, 0.0),
+            eyev,
+            normalv,
+            colour::WHITE,
+        );
         assert_eq!(c1, WHITE);
         assert_eq!(c2, BLACK);
     }
     #[test]
     fn precomp_reflectv() {
         let object = Object::new_plane();
-        let r = Ray::new(point(0.0, 1.0, -1.0), vector(0.0, -(f32::sqrt(2.0)/2.0), f32::sqrt(2.0)/2.0));
-        let i = Intersection::new(f32::sqrt(2.0), object);
-        let comps = r.prepare_computations(&i, Intersections::new(vec![i]));
-        assert_eq!(comps.reflectv, vector(0.0, f32::sqrt(2.0)/2.0, f32::sqrt(2.0)/2.0));
+        let r = Ray::new(
+            point(0.0, 1.0, -1.0),
+            vector(0.0, -(f32::sqrt(2.0) / 2.0), f32::sqrt(2.0) / 2.0),
+        );
+        let i = Intersection::new(f32::sqrt(2.0), object.clone());
+        let comps = r.prepare_computations(&i, Intersections::new(vec![i.clone()]));
+        assert_eq!(
+            comps.reflectv,
+            vector(0.0, f32::sqrt(2.0) / 2.0, f32::sqrt(2.0) / 2.0)
+        );
     }
     fn find_n1_n2_setup(index: usize) -> (f32, f32) {
         let mut a = Object::glass_sphere();
@@ -720,17 +893,19 @@ mod tests {
         c.transform = translation(0.0, 0.0, 0.25);
         c.material.refractive_index = 2.5;
         let r = Ray::new(point(0.0, 0.0, -4.0), vector(0.0, 0.0, 1.0));
-        let xs = Intersections::new(vec![Intersection::new(2.0, a),
-                                                                Intersection::new(2.75, b),
-                                                                Intersection::new(3.25, c),
-                                                                Intersection::new(4.75, b),
-                                                                Intersection::new(5.25, c),
-                                                                Intersection::new(6.0, a)]);
+        let xs = Intersections::new(vec![
+            Intersection::new(2.0, a.clone()),
+            Intersection::new(2.75, b.clone()),
+            Intersection::new(3.25, c.clone()),
+            Intersection::new(4.75, b.clone()),
+            Intersection::new(5.25, c.clone()),
+            Intersection::new(6.0, a.clone()),
+        ]);
         let comps = r.prepare_computations(&xs.inters[index].clone(), xs); //TODO check if this is the right way to borrow
         (comps.n1, comps.n2)
     }
     #[test]
-    fn find_n1_n2_0() {        
+    fn find_n1_n2_0() {
         assert_eq!(find_n1_n2_setup(0), (1.0, 1.5));
         assert_eq!(find_n1_n2_setup(1), (1.5, 2.0));
         assert_eq!(find_n1_n2_setup(2), (2.0, 2.5));
@@ -739,40 +914,201 @@ mod tests {
         assert_eq!(find_n1_n2_setup(5), (1.5, 1.0));
     }
     #[test]
-    fn compute_under_point() {        
+    fn compute_under_point() {
         let r = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
         let mut s = Object::glass_sphere();
-        s.transform = translation(0.0, 0.0, 1.0); 
-        let i = Intersection::new(5.0, s);
-        let xs = Intersections::new(vec![i]);
-        let comps= r.prepare_computations(&i, xs);
-        assert!(comps.under_point.z > DEFAULT_EPSILON/2.0);
+        s.transform = translation(0.0, 0.0, 1.0);
+        let i = Intersection::new(5.0, s.clone());
+        let xs = Intersections::new(vec![i.clone()]);
+        let comps = r.prepare_computations(&i, xs);
+        assert!(comps.under_point.z > DEFAULT_EPSILON / 2.0);
         assert!(comps.point.z < comps.under_point.z);
     }
     #[test]
-    fn compute_schlick() {        
-        let r = Ray::new(point(0.0, 0.0, f32::sqrt(2.0)/2.0), vector(0.0, 1.0, 0.0));
+    fn compute_schlick() {
+        let r = Ray::new(point(0.0, 0.0, f32::sqrt(2.0) / 2.0), vector(0.0, 1.0, 0.0));
         let s = Object::glass_sphere();
-        let xs = Intersections::new(vec![Intersection::new(-f32::sqrt(2.0)/2.0, s),
-                                                                Intersection::new(f32::sqrt(2.0)/2.0, s)]);
-        let comps= r.prepare_computations(&xs.inters[1].clone(), xs);
+        let xs = Intersections::new(vec![
+            Intersection::new(-f32::sqrt(2.0) / 2.0, s.clone()),
+            Intersection::new(f32::sqrt(2.0) / 2.0, s.clone()),
+        ]);
+        let comps = r.prepare_computations(&xs.inters[1].clone(), xs);
         assert_eq!(schlick(comps), 1.0);
     }
     #[test]
-    fn compute_schlick_perpendicular() {        
-        let r = Ray::new(point(0.0, 0.0, 0.0)  , vector(0.0, 1.0, 0.0));
+    fn compute_schlick_perpendicular() {
+        let r = Ray::new(point(0.0, 0.0, 0.0), vector(0.0, 1.0, 0.0));
         let s = Object::glass_sphere();
-        let xs = Intersections::new(vec![Intersection::new(-1.0, s),
-                                                                Intersection::new(1.0, s)]);
-        let comps= r.prepare_computations(&xs.inters[1].clone(), xs);
+        let xs = Intersections::new(vec![
+            Intersection::new(-1.0, s.clone()),
+            Intersection::new(1.0, s.clone()),
+        ]);
+        let comps = r.prepare_computations(&xs.inters[1].clone(), xs);
         assert_relative_eq!(schlick(comps), 0.04);
     }
     #[test]
-    fn compute_schlick_small_angle() {        
-        let r = Ray::new(point(0.0, 0.99, -2.0)  , vector(0.0, 0.0, 1.0));
+    fn compute_schlick_small_angle() {
+        let r = Ray::new(point(0.0, 0.99, -2.0), vector(0.0, 0.0, 1.0));
         let s = Object::glass_sphere();
-        let xs = Intersections::new(vec![Intersection::new(1.8589, s)]);
-        let comps= r.prepare_computations(&xs.inters[0].clone(), xs);
+        let xs = Intersections::new(vec![Intersection::new(1.8589, s.clone())]);
+        let comps = r.prepare_computations(&xs.inters[0].clone(), xs);
         assert_relative_eq!(schlick(comps), 0.4887307);
     }
+    #[test]
+    fn intersect_transformed_sdf() {
+        let mut o = Object::new_sdf(Sdf::new_box(2.0, 2.0, 2.0));
+        o.transform = translation(0.0, 0.0, 2.0) * scale(2.0, 2.0, 2.0);
+        let r = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        let xs = r.intersect(&o);
+        assert_eq!(xs.len(), 2);
+        assert_relative_eq!(xs[0].t, 5.0, epsilon = 0.001);
+        assert_relative_eq!(xs[1].t, 9.0, epsilon = 0.001);
+        assert_relative_eq!(
+            o.normal_at(r.position(xs[0].t)),
+            vector(0.0, 0.0, -1.0),
+            epsilon = 0.001
+        );
+    }
+    #[test]
+    fn intersect_small_distant_sdf() {
+        let mut o = Object::new_sdf(Sdf::new_sphere(1.0));
+        o.transform = scale(0.1, 0.1, 0.1);
+        let r = Ray::new(point(0.0, 0.0, -20.0), vector(0.0, 0.0, 1.0));
+        let xs = r.intersect(&o);
+        assert_eq!(xs.len(), 2);
+        assert_relative_eq!(xs[0].t, 19.9, epsilon = 0.001);
+        assert_relative_eq!(xs[1].t, 20.1, epsilon = 0.001);
+        // Clones share the field and stay equal, separate fields are compared by identity
+        assert_eq!(o.clone(), o);
+        assert_ne!(
+            Object::new_sdf(Sdf::new_sphere(1.0)),
+            Object::new_sdf(Sdf::new_sphere(1.0))
+        );
+    }
+    #[test]
+    fn blended_sdf_fills_gap() {
+        let a = Sdf::new_sphere(1.0).translated(vector(-1.1, 0.0, 0.0));
+        let b = Sdf::new_sphere(1.0).translated(vector(1.1, 0.0, 0.0));
+        let r = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        // The ray passes between the spheres unless they are blended together
+        assert!(r
+            .intersect(&Object::new_sdf(Sdf::smooth_union(
+                a.clone(),
+                b.clone(),
+                0.0
+            )))
+            .is_empty());
+        assert_eq!(
+            r.intersect(&Object::new_sdf(Sdf::smooth_union(a, b, 1.0)))
+                .len(),
+            2
+        );
+    }
+    #[test]
+    fn intersect_torus() {
+        let o = Object::new_torus(1.0, 0.25);
+        let r = Ray::new(point(-5.0, 0.0, 0.0), vector(1.0, 0.0, 0.0));
+        let xs = r.intersect(&o);
+        assert_eq!(xs.len(), 4);
+        for (x, t) in xs.iter().zip([3.75, 4.25, 5.75, 6.25]) {
+            assert_relative_eq!(x.t, t, epsilon = DEFAULT_EPSILON);
+        }
+        // Tori compare by their radii
+        assert_eq!(Object::new_torus(1.0, 0.25), o);
+        assert_ne!(Object::new_torus(1.0, 0.5), o);
+        // Through the tube from above
+        let r = Ray::new(point(0.0, 5.0, 1.0), vector(0.0, -2.0, 0.0));
+        let mut xs = r.intersect(&o);
+        xs.sort();
+        assert_eq!(xs.len(), 2);
+        assert_relative_eq!(xs[0].t, 2.375, epsilon = DEFAULT_EPSILON);
+        assert_relative_eq!(xs[1].t, 2.625, epsilon = DEFAULT_EPSILON);
+        // Through the hole
+        let r = Ray::new(point(0.0, 5.0, 0.0), vector(0.0, -1.0, 0.0));
+        assert!(r.intersect(&o).is_empty());
+    }
+    #[test]
+    fn intersect_distant_torus() {
+        let mut o = Object::new_torus(1.0, 0.25);
+        o.transform = translation(0.0, 0.0, 1000.0) * rot_z(PI / 2.0);
+        let r = Ray::new(point(0.0, 1.0, 0.0), vector(0.0, 0.0, 1.0));
+        let mut xs = r.intersect(&o);
+        xs.sort();
+        assert_eq!(xs.len(), 2);
+        assert_relative_eq!(xs[0].t, 999.25, epsilon = 0.001);
+        assert_relative_eq!(xs[1].t, 1000.75, epsilon = 0.001);
+    }
+    #[test]
+    fn intersect_scaled_heightfield() {
+        let mut o = Object::new_heightfield(Heightfield::from_fn(17, 17, |x, z| x * z).unwrap());
+        o.transform = translation(-5.0, 0.0, -5.0) * scale(10.0, 2.0, 10.0);
+        let r = Ray::new(point(0.0, 10.0, 0.0), vector(0.0, -1.0, 0.0));
+        let xs = r.intersect(&o);
+        assert_eq!(xs.len(), 1);
+        assert_relative_eq!(xs[0].t, 9.5, epsilon = DEFAULT_EPSILON * 10.0);
+        let n = o.normal_at(r.position(xs[0].t));
+        assert!(n.y > 0.9 && n.x < 0.0 && n.z < 0.0);
+        // Objects sharing a heightfield are equal without comparing every height
+        assert_eq!(o.clone(), o);
+        let flat = || Object::new_heightfield(Heightfield::from_fn(2, 2, |_, _| 0.0).unwrap());
+        assert_ne!(flat(), flat());
+    }
+    #[test]
+    fn same_object_by_world_index() {
+        let mut a = Intersection::new(1.0, Object::new_sphere());
+        let mut b = Intersection::new(2.0, Object::new_sphere());
+        assert!(a.same_object(&b));
+        a.index = Some(0);
+        b.index = Some(1);
+        assert!(!a.same_object(&b));
+        b.index = Some(0);
+        assert!(a.same_object(&b));
+    }
+    #[test]
+    fn normal_on_torus() {
+        let o = Object::new_torus(1.0, 0.25);
+        assert_relative_eq!(
+            o.normal_at(point(1.25, 0.0, 0.0)),
+            vector(1.0, 0.0, 0.0),
+            epsilon = DEFAULT_EPSILON
+        );
+        assert_relative_eq!(
+            o.normal_at(point(-0.75, 0.0, 0.0)),
+            vector(1.0, 0.0, 0.0),
+            epsilon = DEFAULT_EPSILON
+        );
+        assert_relative_eq!(
+            o.normal_at(point(0.0, 0.25, 1.0)),
+            vector(0.0, 1.0, 0.0),
+            epsilon = DEFAULT_EPSILON
+        );
+        let p = point(0.0, 0.25 * FRAC_1_SQRT_2, -1.0 - 0.25 * FRAC_1_SQRT_2);
+        assert_relative_eq!(
+            o.normal_at(p),
+            vector(0.0, FRAC_1_SQRT_2, -FRAC_1_SQRT_2),
+            epsilon = DEFAULT_EPSILON
+        );
+    }
+    #[test]
+    fn normal_where_torus_crosses_axis() {
+        // The tube is wider than the ring, so the surface crosses the axis at y = +-0.6
+        let o = Object::new_torus(0.8, 1.0);
+        assert_relative_eq!(
+            o.normal_at(point(0.0, 0.6, 0.0)),
+            vector(0.0, 1.0, 0.0),
+            epsilon = DEFAULT_EPSILON
+        );
+        assert_relative_eq!(
+            o.normal_at(point(0.0, -0.6, 0.0)),
+            vector(0.0, -1.0, 0.0),
+            epsilon = DEFAULT_EPSILON
+        );
+        // Where the tube just touches the axis the normal is still a unit vector
+        let horn = Object::new_torus(1.0, 1.0);
+        assert_relative_eq!(
+            horn.normal_at(point(0.0, 0.0, 0.0)).magnitude(),
+            1.0,
+            epsilon = DEFAULT_EPSILON
+        );
+    }
 }
@@ -0,0 +1,114 @@
+use std::{cell::Cell, f32::consts::PI};
+
+use crate::{
+    bump::tangent_frame,
+    tuple::{vector, Tuple},
+};
+
+thread_local! {
+    static STATE: Cell<u64> = const { Cell::new(0x853c_49e6_748f_ea9b) };
+}
+/// Return a pseudo random number between 0.0 (inclusive) and 1.0 (exclusive) from a per thread xorshift generator
+pub fn random() -> f32 {
+    STATE.with(|state| {
+        let mut x = state.get();
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        state.set(x);
+        (x.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 40) as f32 / (1u64 << 24) as f32
+    })
+}
+/// Restart the random number generator of this thread from a seed
+pub fn seed(seed: u64) {
+    STATE.with(|state| state.set(seed.max(1)));
+}
+/// A random direction in the hemisphere around normal, more likely close to the normal with a pdf of cos(theta)/pi
+pub fn cosine_hemisphere(normal: Tuple) -> Tuple {
+    let u1 = random();
+    let u2 = random();
+    let r = u1.sqrt();
+    let phi = 2.0 * PI * u2;
+    let (tangent, bitangent) = tangent_frame(normal);
+    (tangent * (r * phi.cos()) + bitangent * (r * phi.sin()) + normal * (1.0 - u1).max(0.0).sqrt())
+        .normalize()
+}
+/// A random direction within half_angle radians of direction, with every direction in the cone equally likely
+pub fn cone(direction: Tuple, half_angle: f32) -> Tuple {
+    let cos_max = half_angle.min(PI).cos();
+    let cos_theta = 1.0 - random() * (1.0 - cos_max);
+    let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+    let phi = 2.0 * PI * random();
+    let (tangent, bitangent) = tangent_frame(direction);
+    (tangent * (sin_theta * phi.cos())
+        + bitangent * (sin_theta * phi.sin())
+        + direction * cos_theta)
+        .normalize()
+}
+/// A random direction with every direction equally likely
+pub fn uniform_sphere() -> Tuple {
+    let z = 1.0 - 2.0 * random();
+    let r = (1.0 - z * z).max(0.0).sqrt();
+    let phi = 2.0 * PI * random();
+    vector(r * phi.cos(), r * phi.sin(), z)
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+
+    use super::{cone, cosine_hemisphere, random, seed, uniform_sphere};
+    use crate::{tuple::vector, DEFAULT_EPSILON};
+
+    #[test]
+    fn random_is_in_unit_range() {
+        let mut sum = 0.0;
+        for _ in 0..10000 {
+            let r = random();
+            assert!((0.0..1.0).contains(&r));
+            sum += r;
+        }
+        assert_relative_eq!(sum / 10000.0, 0.5, epsilon = 0.02);
+    }
+    #[test]
+    fn seeding_repeats_sequence() {
+        seed(42);
+        let a = [random(), random(), random()];
+        seed(42);
+        let b = [random(), random(), random()];
+        assert_eq!(a, b);
+    }
+    #[test]
+    fn cosine_hemisphere_stays_above_surface() {
+        let normal = vector(0.3, 0.9, -0.2).normalize();
+        let mut mean_cos = 0.0;
+        for _ in 0..5000 {
+            let d = cosine_hemisphere(normal);
+            assert_relative_eq!(d.magnitude(), 1.0, epsilon = DEFAULT_EPSILON * 10.0);
+            assert!(d.dot(normal) >= 0.0);
+            mean_cos += d.dot(normal);
+        }
+        // The mean of cos(theta) for a cosine weighted distribution is 2/3
+        assert_relative_eq!(mean_cos / 5000.0, 2.0 / 3.0, epsilon = 0.02);
+    }
+    #[test]
+    fn uniform_sphere_is_balanced() {
+        let mut sum = vector(0.0, 0.0, 0.0);
+        for _ in 0..5000 {
+            let d = uniform_sphere();
+            assert_relative_eq!(d.magnitude(), 1.0, epsilon = DEFAULT_EPSILON * 10.0);
+            sum = sum + d;
+        }
+        assert!((sum / 5000.0).magnitude() < 0.05);
+    }
+    #[test]
+    fn cone_stays_within_angle() {
+        let direction = vector(-0.5, 0.2, 0.8).normalize();
+        let half_angle = 0.3_f32;
+        for _ in 0..2000 {
+            let d = cone(direction, half_angle);
+            assert_relative_eq!(d.magnitude(), 1.0, epsilon = DEFAULT_EPSILON * 10.0);
+            assert!(d.dot(direction) >= half_angle.cos() - DEFAULT_EPSILON);
+        }
+    }
+}
@@ -0,0 +1,296 @@
+use std::sync::Arc;
+
+use crate::tuple::{vector, Tuple};
+
+/// Most steps taken along a ray before giving up.
+const MAX_STEPS: usize = 512;
+/// Smallest step, so rays close to a surface still cross it instead of creeping towards it.
+const MIN_STEP: f32 = 0.001;
+/// Bisection steps used to refine a surface crossing.
+const REFINE_STEPS: usize = 24;
+/// Offset used for the central differences of the normal.
+const NORMAL_STEP: f32 = 0.0005;
+
+/// A shape described by the signed distance from any point to its surface, negative inside.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Sdf {
+    Sphere {
+        radius: f32,
+    },
+    /// A box centred on the origin, half_size holds half its size along each axis.
+    Cuboid {
+        half_size: Tuple,
+    },
+    /// A ring around the y axis, major is the radius of the ring and minor the radius of its tube.
+    Torus {
+        major: f32,
+        minor: f32,
+    },
+    /// A line segment from a to b thickened by radius.
+    Capsule {
+        a: Tuple,
+        b: Tuple,
+        radius: f32,
+    },
+    /// Both shapes, blended together where they are closer than k.
+    SmoothUnion(Arc<Sdf>, Arc<Sdf>, f32),
+    /// The first shape with the second carved out of it, blended over k.
+    SmoothSubtract(Arc<Sdf>, Arc<Sdf>, f32),
+    /// The shape moved by an offset.
+    Translated(Arc<Sdf>, Tuple),
+}
+impl Sdf {
+    pub fn new_sphere(radius: f32) -> Sdf {
+        Sdf::Sphere { radius }
+    }
+    /// Create a box from its full size along each axis
+    pub fn new_box(x: f32, y: f32, z: f32) -> Sdf {
+        Sdf::Cuboid {
+            half_size: vector(x / 2.0, y / 2.0, z / 2.0),
+        }
+    }
+    pub fn new_torus(major: f32, minor: f32) -> Sdf {
+        Sdf::Torus { major, minor }
+    }
+    pub fn new_capsule(a: Tuple, b: Tuple, radius: f32) -> Sdf {
+        Sdf::Capsule { a, b, radius }
+    }
+    /// Join two shapes, a k of 0.0 gives a sharp union
+    pub fn smooth_union(a: Sdf, b: Sdf, k: f32) -> Sdf {
+        Sdf::SmoothUnion(Arc::new(a), Arc::new(b), k)
+    }
+    /// Carve b out of a, a k of 0.0 gives a sharp cut
+    pub fn smooth_subtract(a: Sdf, b: Sdf, k: f32) -> Sdf {
+        Sdf::SmoothSubtract(Arc::new(a), Arc::new(b), k)
+    }
+    /// Move the shape by the offset vector
+    pub fn translated(self, offset: Tuple) -> Sdf {
+        Sdf::Translated(Arc::new(self), offset)
+    }
+    /// The signed distance from a point to the surface, negative inside the shape
+    pub fn distance(&self, p: Tuple) -> f32 {
+        match self {
+            Sdf::Sphere { radius } => length(p.x, p.y, p.z) - radius,
+            Sdf::Cuboid { half_size } => {
+                let (qx, qy, qz) = (
+                    p.x.abs() - half_size.x,
+                    p.y.abs() - half_size.y,
+                    p.z.abs() - half_size.z,
+                );
+                length(qx.max(0.0), qy.max(0.0), qz.max(0.0)) + qx.max(qy).max(qz).min(0.0)
+            }
+            Sdf::Torus { major, minor } => length(length(p.x, 0.0, p.z) - major, p.y, 0.0) - minor,
+            Sdf::Capsule { a, b, radius } => {
+                let pa = p - *a;
+                let ba = *b - *a;
+                let h = (pa.dot(ba) / ba.dot(ba)).clamp(0.0, 1.0);
+                let d = pa - ba * h;
+                length(d.x, d.y, d.z) - radius
+            }
+            Sdf::SmoothUnion(a, b, k) => {
+                let (d1, d2) = (a.distance(p), b.distance(p));
+                if *k <= 0.0 {
+                    return d1.min(d2);
+                }
+                let h = (0.5 + 0.5 * (d2 - d1) / k).clamp(0.0, 1.0);
+                d2 + (d1 - d2) * h - k * h * (1.0 - h)
+            }
+            Sdf::SmoothSubtract(a, b, k) => {
+                let (d1, d2) = (a.distance(p), b.distance(p));
+                if *k <= 0.0 {
+                    return d1.max(-d2);
+                }
+                let h = (0.5 - 0.5 * (d1 + d2) / k).clamp(0.0, 1.0);
+                d1 + (-d2 - d1) * h + k * h * (1.0 - h)
+            }
+            Sdf::Translated(sdf, offset) => sdf.distance(p - *offset),
+        }
+    }
+    /// The normal at a point on the surface, from the central difference gradient of the distance
+    pub fn normal(&self, p: Tuple) -> Tuple {
+        let dx = vector(NORMAL_STEP, 0.0, 0.0);
+        let dy = vector(0.0, NORMAL_STEP, 0.0);
+        let dz = vector(0.0, 0.0, NORMAL_STEP);
+        vector(
+            self.distance(p + dx) - self.distance(p - dx),
+            self.distance(p + dy) - self.distance(p - dy),
+            self.distance(p + dz) - self.distance(p - dz),
+        )
+        .normalize()
+    }
+    /// The radius of a sphere around the origin that contains the whole shape
+    pub fn bounding_radius(&self) -> f32 {
+        match self {
+            Sdf::Sphere { radius } => *radius,
+            Sdf::Cuboid { half_size } => half_size.magnitude(),
+            Sdf::Torus { major, minor } => major + minor,
+            Sdf::Capsule { a, b, radius } => {
+                length(a.x, a.y, a.z).max(length(b.x, b.y, b.z)) + radius
+            }
+            // Blending can only bulge the union out by a quarter of k
+            Sdf::SmoothUnion(a, b, k) => {
+                a.bounding_radius().max(b.bounding_radius()) + k.max(0.0) / 4.0
+            }
+            // Carving never adds to the first shape
+            Sdf::SmoothSubtract(a, _, _) => a.bounding_radius(),
+            Sdf::Translated(sdf, offset) => sdf.bounding_radius() + offset.magnitude(),
+        }
+    }
+    /// Sphere trace along a ray, returning the t of every surface crossing in front of its origin. Only the part of
+    /// the ray inside the bounding sphere is marched, so the shape is found however far away the ray starts.
+    pub fn march(&self, origin: Tuple, direction: Tuple) -> Vec<f32> {
+        let speed = direction.magnitude();
+        let unit = direction * (1.0 / speed);
+        let mut crossings = Vec::new();
+        let radius = self.bounding_radius() + MIN_STEP;
+        let from_centre = vector(origin.x, origin.y, origin.z);
+        let b = from_centre.dot(unit);
+        let discriminant = b * b - (from_centre.dot(from_centre) - radius * radius);
+        if discriminant < 0.0 {
+            return crossings;
+        }
+        let end = -b + discriminant.sqrt();
+        let mut s = (-b - discriminant.sqrt()).max(0.0);
+        let mut d = self.distance(origin + unit * s);
+        for _ in 0..MAX_STEPS {
+            if s > end {
+                break;
+            }
+            let next_s = s + d.abs().max(MIN_STEP);
+            let next_d = self.distance(origin + unit * next_s);
+            if (d < 0.0) != (next_d < 0.0) {
+                crossings.push(self.refine(origin, unit, s, next_s, d < 0.0) / speed);
+            }
+            s = next_s;
+            d = next_d;
+        }
+        crossings
+    }
+    /// Bisect between two distances along the ray either side of the surface
+    fn refine(
+        &self,
+        origin: Tuple,
+        unit: Tuple,
+        mut low: f32,
+        mut high: f32,
+        inside_low: bool,
+    ) -> f32 {
+        for _ in 0..REFINE_STEPS {
+            let mid = (low + high) / 2.0;
+            if (self.distance(origin + unit * mid) < 0.0) == inside_low {
+                low = mid;
+            } else {
+                high = mid;
+            }
+        }
+        (low + high) / 2.0
+    }
+}
+fn length(x: f32, y: f32, z: f32) -> f32 {
+    (x * x + y * y + z * z).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+
+    use super::Sdf;
+    use crate::{
+        tuple::{point, vector},
+        DEFAULT_EPSILON,
+    };
+
+    #[test]
+    fn primitive_distances() {
+        let sphere = Sdf::new_sphere(1.0);
+        assert_relative_eq!(sphere.distance(point(0.0, 3.0, 0.0)), 2.0);
+        assert_relative_eq!(sphere.distance(point(0.0, 0.0, 0.0)), -1.0);
+        let cube = Sdf::new_box(2.0, 4.0, 2.0);
+        assert_relative_eq!(cube.distance(point(3.0, 0.0, 0.0)), 2.0);
+        assert_relative_eq!(cube.distance(point(0.0, 0.0, 0.0)), -1.0);
+        assert_relative_eq!(cube.distance(point(2.0, 3.0, 0.0)), f32::sqrt(2.0));
+        let torus = Sdf::new_torus(2.0, 0.5);
+        assert_relative_eq!(torus.distance(point(2.0, 0.0, 0.0)), -0.5);
+        assert_relative_eq!(torus.distance(point(0.0, 0.0, 0.0)), 1.5);
+        assert_relative_eq!(torus.distance(point(0.0, 1.0, -2.0)), 0.5);
+        let capsule = Sdf::new_capsule(point(0.0, -1.0, 0.0), point(0.0, 1.0, 0.0), 0.5);
+        assert_relative_eq!(capsule.distance(point(1.0, 0.5, 0.0)), 0.5);
+        assert_relative_eq!(capsule.distance(point(0.0, 3.0, 0.0)), 1.5);
+    }
+    #[test]
+    fn smooth_operators() {
+        let a = Sdf::new_sphere(1.0).translated(vector(-0.9, 0.0, 0.0));
+        let b = Sdf::new_sphere(1.0).translated(vector(0.9, 0.0, 0.0));
+        let sharp = Sdf::smooth_union(a.clone(), b.clone(), 0.0);
+        let smooth = Sdf::smooth_union(a.clone(), b.clone(), 0.5);
+        let p = point(0.0, 0.5, 0.0);
+        assert_relative_eq!(sharp.distance(p), a.distance(p).min(b.distance(p)));
+        // Blending fills in the crease between the spheres
+        assert!(smooth.distance(p) < sharp.distance(p));
+        let far = point(0.0, 10.0, 0.0);
+        assert!(smooth.distance(far) <= sharp.distance(far));
+        let cut = Sdf::smooth_subtract(
+            Sdf::new_sphere(1.0),
+            Sdf::new_box(4.0, 4.0, 4.0).translated(vector(2.0, 0.0, 0.0)),
+            0.0,
+        );
+        assert!(cut.distance(point(0.5, 0.0, 0.0)) > 0.0);
+        assert!(cut.distance(point(-0.5, 0.0, 0.0)) < 0.0);
+    }
+    #[test]
+    fn march_finds_entry_and_exit() {
+        let sphere = Sdf::new_sphere(1.0);
+        let xs = sphere.march(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        assert_eq!(xs.len(), 2);
+        assert_relative_eq!(xs[0], 4.0, epsilon = 0.0001);
+        assert_relative_eq!(xs[1], 6.0, epsilon = 0.0001);
+        // Direction length scales t, as for transformed rays
+        let xs = sphere.march(point(0.0, 0.0, 0.0), vector(0.0, 0.0, 2.0));
+        assert_eq!(xs.len(), 1);
+        assert_relative_eq!(xs[0], 0.5, epsilon = 0.0001);
+        assert!(sphere
+            .march(point(0.0, 2.0, -5.0), vector(0.0, 0.0, 1.0))
+            .is_empty());
+        // Marching starts at the bounding sphere, however far away the ray begins
+        let xs = sphere.march(point(0.0, 0.0, -150.0), vector(0.0, 0.0, 1.0));
+        assert_eq!(xs.len(), 2);
+        assert_relative_eq!(xs[0], 149.0, epsilon = 0.001);
+        assert_relative_eq!(xs[1], 151.0, epsilon = 0.001);
+    }
+    #[test]
+    fn bounding_radius_contains_shape() {
+        let shapes = [
+            Sdf::new_sphere(1.5),
+            Sdf::new_box(2.0, 4.0, 2.0),
+            Sdf::new_torus(2.0, 0.5),
+            Sdf::new_capsule(point(0.0, -1.0, 0.0), point(0.0, 1.0, 0.0), 0.5),
+            Sdf::smooth_union(
+                Sdf::new_sphere(1.0),
+                Sdf::new_sphere(1.0).translated(vector(1.5, 0.0, 0.0)),
+                0.5,
+            ),
+        ];
+        for shape in shapes {
+            let r = shape.bounding_radius();
+            for direction in [
+                vector(1.0, 0.0, 0.0),
+                vector(0.0, -1.0, 0.0),
+                vector(1.0, 1.0, 1.0).normalize(),
+            ] {
+                assert!(shape.distance(point(0.0, 0.0, 0.0) + direction * r) >= -DEFAULT_EPSILON);
+            }
+        }
+    }
+    #[test]
+    fn normal_from_gradient() {
+        let sphere = Sdf::new_sphere(1.0);
+        let n = sphere.normal(point(0.0, 0.0, -1.0));
+        assert_relative_eq!(n, vector(0.0, 0.0, -1.0), epsilon = DEFAULT_EPSILON * 10.0);
+        let cube = Sdf::new_box(2.0, 2.0, 2.0);
+        assert_relative_eq!(
+            cube.normal(point(1.0, 0.3, -0.2)),
+            vector(1.0, 0.0, 0.0),
+            epsilon = DEFAULT_EPSILON * 10.0
+        );
+    }
+}
@@ -1,17 +1,78 @@
+use std::{f32::consts::PI, sync::Arc};
+
 use crate::{
+    bump::BumpMap,
+    canvas::Canvas,
     colour::{self, Colour},
+    dispersion::Dispersion,
+    heightfield::Heightfield,
     matrix::{self, identity, Matrix4x4},
-    tuple::{self, point, Tuple}, DEFAULT_EPSILON,
+    noise::{cellular, fbm, fbm_vector, turbulence, CellularOutput, DistanceMetric},
+    pbr::PbrMaterial,
+    ramp::ColourRamp,
+    sampling,
+    sdf::Sdf,
+    texture::{ImageTexture, TextureFilter, UvMapping},
+    tuple::{self, point, Tuple},
+    volume::Medium,
+    DEFAULT_EPSILON,
 };
 /// An enum of all the shapes that can be intersected by a ray.
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub enum Shape {
     Sphere(),
     Test(),
     Plane(),
+    /// A shape given by a signed distance field, intersected by sphere tracing.
+    Sdf(Arc<Sdf>),
+    /// A ring around the y axis, major is the radius of the ring and minor the radius of its tube.
+    Torus {
+        major: f32,
+        minor: f32,
+    },
+    /// Terrain over the unit square of the xz-plane, intersected by walking its grid.
+    Heightfield(Arc<Heightfield>),
+    /// A disc on the xz-plane centred on the origin.
+    Disc {
+        radius: f32,
+    },
+    /// A flat ring on the xz-plane, the disc of radius outer with the disc of radius inner cut out.
+    Annulus {
+        inner: f32,
+        outer: f32,
+    },
+    /// A rectangle on the xz-plane centred on the origin, width along x and depth along z.
+    Rectangle {
+        width: f32,
+        depth: f32,
+    },
+}
+/// Signed distance fields and heightfields are equal only when they are the same shared `Arc`, so copies of an
+/// object match each other but separately built shapes never do. Every other shape compares its dimensions.
+impl PartialEq for Shape {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Shape::Sphere(), Shape::Sphere())
+            | (Shape::Test(), Shape::Test())
+            | (Shape::Plane(), Shape::Plane()) => true,
+            (Shape::Sdf(a), Shape::Sdf(b)) => Arc::ptr_eq(a, b),
+            (Shape::Torus { major: a, minor: b }, Shape::Torus { major: c, minor: d }) => {
+                a == c && b == d
+            }
+            (Shape::Disc { radius: a }, Shape::Disc { radius: b }) => a == b,
+            (Shape::Annulus { inner: a, outer: b }, Shape::Annulus { inner: c, outer: d }) => {
+                a == c && b == d
+            }
+            (Shape::Rectangle { width: a, depth: b }, Shape::Rectangle { width: c, depth: d }) => {
+                a == c && b == d
+            }
+            (Shape::Heightfield(a), Shape::Heightfield(b)) => Arc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
 }
 /// A sphere.
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct Object {
     pub transform: matrix::Matrix4x4,
     pub material: Material,
@@ -53,22 +114,116 @@ impl Object {
             shape: Shape::Plane(),
         }
     }
+    /// Create a new torus from its ring and tube radii
+    pub fn new_torus(major: f32, minor: f32) -> Object {
+        Object {
+            transform: matrix::identity(),
+            material: Material::new(),
+            shape: Shape::Torus { major, minor },
+        }
+    }
+    /// Create a new disc on the xz-plane
+    pub fn new_disc(radius: f32) -> Object {
+        Object {
+            transform: matrix::identity(),
+            material: Material::new(),
+            shape: Shape::Disc { radius },
+        }
+    }
+    /// Create a new flat ring on the xz-plane from its inner and outer radii
+    pub fn new_annulus(inner: f32, outer: f32) -> Object {
+        Object {
+            transform: matrix::identity(),
+            material: Material::new(),
+            shape: Shape::Annulus { inner, outer },
+        }
+    }
+    /// Create a new rectangle on the xz-plane from its full width along x and depth along z
+    pub fn new_rectangle(width: f32, depth: f32) -> Object {
+        Object {
+            transform: matrix::identity(),
+            material: Material::new(),
+            shape: Shape::Rectangle { width, depth },
+        }
+    }
+    /// Create a new terrain shape from a heightfield
+    pub fn new_heightfield(heightfield: Heightfield) -> Object {
+        Object {
+            transform: matrix::identity(),
+            material: Material::new(),
+            shape: Shape::Heightfield(Arc::new(heightfield)),
+        }
+    }
+    /// Create a new shape from a signed distance field
+    pub fn new_sdf(sdf: Sdf) -> Object {
+        Object {
+            transform: matrix::identity(),
+            material: Material::new(),
+            shape: Shape::Sdf(Arc::new(sdf)),
+        }
+    }
     /// Compute the objects normal at a particular world point
-    pub fn normal_at(self, world_point: tuple::Tuple) -> tuple::Tuple {
+    pub fn normal_at(&self, world_point: tuple::Tuple) -> tuple::Tuple {
         let object_point = self.transform.inverse() * world_point;
-        let object_normal;
-        match self.shape {
-            Shape::Sphere() => object_normal = object_point - point(0.0, 0.0, 0.0),
-            Shape::Test() => object_normal = point(0.0, 0.0, 0.0),
-            Shape::Plane() => object_normal = point(0.0, 1.0, 0.0),
+        self.world_normal(self.object_normal_at(object_point))
+    }
+    /// Compute the normal used for shading at a world point, the geometric normal perturbed by the material bump map
+    pub fn shading_normal_at(&self, world_point: tuple::Tuple) -> tuple::Tuple {
+        let object_point = self.transform.inverse() * world_point;
+        let mut object_normal = self.object_normal_at(object_point);
+        if let Some(bump) = &self.material.bump {
+            object_normal.w = 0.0;
+            object_normal = bump.perturb(object_point, object_normal.normalize());
+        }
+        self.world_normal(object_normal)
+    }
+    /// Compute the normal of the shape at a point in object space
+    fn object_normal_at(&self, object_point: Tuple) -> Tuple {
+        match &self.shape {
+            Shape::Sphere() => object_point - point(0.0, 0.0, 0.0),
+            Shape::Test() => point(0.0, 0.0, 0.0),
+            Shape::Plane()
+            | Shape::Disc { .. }
+            | Shape::Annulus { .. }
+            | Shape::Rectangle { .. } => point(0.0, 1.0, 0.0),
+            Shape::Sdf(sdf) => sdf.normal(object_point),
+            Shape::Heightfield(heightfield) => heightfield.normal(object_point),
+            Shape::Torus { major, .. } => {
+                // Away from the closest point on the centre line of the tube
+                let ring = f32::sqrt(object_point.x.powi(2) + object_point.z.powi(2));
+                if ring < DEFAULT_EPSILON {
+                    // A tube wider than the ring crosses the axis, where the closest points on the centre line
+                    // surround the point evenly, so the normal runs along the axis
+                    return tuple::vector(0.0, object_point.y.signum(), 0.0);
+                }
+                let scale = major / ring;
+                tuple::vector(
+                    object_point.x - object_point.x * scale,
+                    object_point.y,
+                    object_point.z - object_point.z * scale,
+                )
+            }
         }
+    }
+    /// The (u, v) coordinates of a world point on a flat bounded shape, spanning 0.0 to 1.0 over its surface
+    pub fn uv_at(&self, world_point: Tuple) -> Option<(f32, f32)> {
+        let mapping = match self.shape {
+            Shape::Disc { radius } => UvMapping::Disc { radius },
+            Shape::Annulus { inner, outer } => UvMapping::Annulus { inner, outer },
+            Shape::Rectangle { width, depth } => UvMapping::Rectangle { width, depth },
+            _ => return None,
+        };
+        Some(mapping.map(self.transform.inverse() * world_point))
+    }
+    /// Transform an object space normal to a normalized world space normal
+    fn world_normal(&self, object_normal: Tuple) -> Tuple {
         let mut world_normal = self.transform.inverse().transpose() * object_normal;
         world_normal.w = 0.0;
         world_normal.normalize()
     }
     /// Compute the pattern colour at the given point
     pub fn pattern_at(&self, world_point: Tuple) -> Colour {
-        if let Some(pattern) = self.material.pattern {
+        if let Some(pattern) = &self.material.pattern {
             let object_point = self.transform.inverse() * world_point;
             let pattern_point = pattern.transformation.inverse() * object_point;
             pattern.pattern_at(pattern_point)
@@ -82,8 +237,58 @@ impl Default for Object {
         Self::new()
     }
 }
-/// The optical properties of a shape
+/// Rough reflection and refraction, several rays are spread around the perfect direction and averaged.
+/// Each sample recurses, so the ray count grows as samples to the power of the recursion depth.
 #[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Glossy {
+    /// 0.0 is a perfect mirror, 1.0 spreads rays over the whole hemisphere.
+    pub roughness: f32,
+    pub samples: usize,
+}
+impl Glossy {
+    /// Create a glossy setting from a roughness between 0.0 and 1.0 and a sample count
+    pub fn new(roughness: f32, samples: usize) -> Glossy {
+        Glossy {
+            roughness: roughness.clamp(0.0, 1.0),
+            samples: samples.max(1),
+        }
+    }
+    /// A random direction in the lobe around the perfect direction, kept on the same side of the surface as it
+    pub fn scatter(&self, direction: Tuple, normal: Tuple) -> Tuple {
+        let scattered = sampling::cone(direction, self.roughness * PI / 2.0);
+        if scattered.dot(normal).signum() == direction.dot(normal).signum() {
+            scattered
+        } else {
+            direction
+        }
+    }
+}
+/// Light that enters a surface and scatters around inside the object before leaving, as in wax, skin and marble.
+/// It is traced as random walks through the object and replaces the Phong diffuse term.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Subsurface {
+    /// The fraction of each channel kept at every scattering event inside the object.
+    pub colour: Colour,
+    /// Average distance light travels inside the object between scattering events.
+    pub mean_free_path: f32,
+    /// Random walks traced per hit.
+    pub samples: usize,
+    /// Scattering events after which a walk that has not left the object is dropped.
+    pub max_steps: usize,
+}
+impl Subsurface {
+    /// Create a subsurface setting from the scattering colour, mean free path and number of walks per hit
+    pub fn new(colour: Colour, mean_free_path: f32, samples: usize) -> Subsurface {
+        Subsurface {
+            colour,
+            mean_free_path,
+            samples: samples.max(1),
+            max_steps: 64,
+        }
+    }
+}
+/// The optical properties of a shape
+#[derive(Debug, PartialEq, Clone)]
 pub struct Material {
     pub colour: colour::Colour,
     pub ambient: f32,
@@ -94,6 +299,25 @@ pub struct Material {
     pub reflective: f32,
     pub transparency: f32,
     pub refractive_index: f32,
+    pub bump: Option<BumpMap>,
+    /// Shade with a physically based metal/roughness model instead of Phong.
+    pub pbr: Option<PbrMaterial>,
+    /// Light given off by the surface, scaled by emission_strength.
+    pub emissive: Colour,
+    pub emission_strength: f32,
+    /// The colour light tends towards as it travels through a transparent material.
+    pub absorption_colour: Colour,
+    /// How quickly light is absorbed per unit of distance, 0.0 disables absorption.
+    pub absorption_density: f32,
+    /// Wavelength dependent refractive index, used instead of refractive_index when light refracts through the surface.
+    pub dispersion: Option<Dispersion>,
+    /// Blur reflections and refractions, None traces a single perfect ray.
+    pub glossy: Option<Glossy>,
+    /// Objects that do not cast shadows let light through to everything behind them.
+    pub casts_shadow: bool,
+    /// Fill the object with a medium such as smoke instead of shading its surface.
+    pub volume: Option<Medium>,
+    pub subsurface: Option<Subsurface>,
 }
 impl Material {
     /// Create a new default material
@@ -108,100 +332,269 @@ impl Material {
             reflective: 0.0,
             transparency: 0.0,
             refractive_index: 1.0,
+            bump: None,
+            pbr: None,
+            emissive: colour::BLACK,
+            emission_strength: 1.0,
+            absorption_colour: colour::WHITE,
+            absorption_density: 0.0,
+            dispersion: None,
+            glossy: None,
+            casts_shadow: true,
+            volume: None,
+            subsurface: None,
         }
     }
+    /// The light given off by the surface
+    pub fn emission(&self) -> Colour {
+        self.emissive * self.emission_strength
+    }
+    /// The fraction of each colour channel that survives travelling a distance inside the material, following the Beer-Lambert law
+    pub fn transmittance(&self, distance: f32) -> Colour {
+        let channel = |c: f32| {
+            let coefficient = (1.0 - c) * self.absorption_density;
+            if coefficient <= 0.0 {
+                1.0
+            } else {
+                (-coefficient * distance).exp()
+            }
+        };
+        let c = self.absorption_colour;
+        Colour::new(channel(c.red), channel(c.green), channel(c.blue))
+    }
 }
-#[derive(Debug, PartialEq, Clone, Copy)]
+impl Default for Material {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+#[derive(Debug, PartialEq, Clone)]
 pub enum PatternType {
     Test(),
     Striped(),
     Gradient(),
     Ring(),
     Checkers(),
+    Image(ImageTexture),
+    /// The average of both slots.
+    Blend(),
+    /// A linear interpolation from the first slot to the second by the given fraction.
+    Mix(f32),
+    /// Fractal noise blending from the first slot to the second.
+    Noise {
+        octaves: usize,
+    },
+    /// The first slot evaluated at a point jittered by fractal noise, the second slot is unused.
+    Perturbed {
+        scale: f32,
+        octaves: usize,
+    },
+    /// Veins along x from a sine wave disturbed by turbulence.
+    Marble {
+        octaves: usize,
+        turbulence: f32,
+    },
+    /// Rings around the y axis disturbed by fractal noise.
+    Wood {
+        octaves: usize,
+        turbulence: f32,
+    },
+    /// Worley cellular noise blending from the first slot to the second.
+    Cellular {
+        metric: DistanceMetric,
+        output: CellularOutput,
+    },
+    /// A gradient through any number of colour stops, the slots are unused.
+    Ramp(ColourRamp),
 }
-#[derive(Debug, PartialEq, Clone, Copy)]
+/// What fills one of the slots of a pattern, either a flat colour or another pattern.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Paint {
+    Colour(Colour),
+    /// A nested pattern, evaluated through its own transformation.
+    Pattern(Arc<Pattern>),
+}
+impl Paint {
+    /// Compute the colour of the slot at a point in the space of the pattern that owns it
+    pub fn colour_at(&self, point: Tuple) -> Colour {
+        match self {
+            Paint::Colour(colour) => *colour,
+            Paint::Pattern(pattern) => pattern.pattern_at(pattern.transformation.inverse() * point),
+        }
+    }
+}
+impl From<Colour> for Paint {
+    fn from(colour: Colour) -> Self {
+        Paint::Colour(colour)
+    }
+}
+impl From<Pattern> for Paint {
+    fn from(pattern: Pattern) -> Self {
+        Paint::Pattern(Arc::new(pattern))
+    }
+}
+#[derive(Debug, PartialEq, Clone)]
 pub struct Pattern {
-    pub c1: colour::Colour,
-    pub c2: colour::Colour,
+    pub c1: Paint,
+    pub c2: Paint,
     pub pattern_type: PatternType,
     pub transformation: Matrix4x4,
 }
 impl Pattern {
-    pub fn new_test() -> Pattern {
+    /// Create a pattern of the given type, the slots may be colours or other patterns
+    pub fn new(c1: impl Into<Paint>, c2: impl Into<Paint>, pattern_type: PatternType) -> Pattern {
         Pattern {
-            c1: colour::BLACK,
-            c2: colour::WHITE,
-            pattern_type: PatternType::Test(),
+            c1: c1.into(),
+            c2: c2.into(),
+            pattern_type,
             transformation: identity(),
         }
     }
-    pub fn new_striped(c1: colour::Colour, c2: colour::Colour) -> Pattern {
-        Pattern {
-            c1,
-            c2,
-            pattern_type: PatternType::Striped(),
-            transformation: identity(),
-        }
+    pub fn new_test() -> Pattern {
+        Pattern::new(colour::BLACK, colour::WHITE, PatternType::Test())
     }
-    pub fn new_gradient(c1: colour::Colour, c2: colour::Colour) -> Pattern {
-        Pattern {
-            c1,
-            c2,
-            pattern_type: PatternType::Gradient(),
-            transformation: identity(),
-        }
+    pub fn new_striped(c1: impl Into<Paint>, c2: impl Into<Paint>) -> Pattern {
+        Pattern::new(c1, c2, PatternType::Striped())
     }
-    pub fn new_ring(c1: colour::Colour, c2: colour::Colour) -> Pattern {
-        Pattern {
+    pub fn new_gradient(c1: impl Into<Paint>, c2: impl Into<Paint>) -> Pattern {
+        Pattern::new(c1, c2, PatternType::Gradient())
+    }
+    pub fn new_ring(c1: impl Into<Paint>, c2: impl Into<Paint>) -> Pattern {
+        Pattern::new(c1, c2, PatternType::Ring())
+    }
+    pub fn new_checkers(c1: impl Into<Paint>, c2: impl Into<Paint>) -> Pattern {
+        Pattern::new(c1, c2, PatternType::Checkers())
+    }
+    /// Create a pattern that samples an image through a UV mapping
+    pub fn new_image(image: Arc<Canvas>, mapping: UvMapping, filter: TextureFilter) -> Pattern {
+        let texture = ImageTexture::new(image, mapping, filter);
+        Pattern::new(colour::BLACK, colour::WHITE, PatternType::Image(texture))
+    }
+    /// Create a pattern that averages two colours or patterns
+    pub fn new_blend(c1: impl Into<Paint>, c2: impl Into<Paint>) -> Pattern {
+        Pattern::new(c1, c2, PatternType::Blend())
+    }
+    /// Create a pattern that interpolates between two colours or patterns, a fraction of 0.0 gives only the first
+    pub fn new_mix(c1: impl Into<Paint>, c2: impl Into<Paint>, fraction: f32) -> Pattern {
+        Pattern::new(c1, c2, PatternType::Mix(fraction))
+    }
+    /// Create a pattern that blends between two colours or patterns using fractal noise
+    pub fn new_noise(c1: impl Into<Paint>, c2: impl Into<Paint>) -> Pattern {
+        Pattern::new(c1, c2, PatternType::Noise { octaves: 4 })
+    }
+    /// Create a pattern that jitters the points passed to another pattern by up to roughly scale units
+    pub fn new_perturbed(pattern: impl Into<Paint>, scale: f32) -> Pattern {
+        Pattern::new(
+            pattern,
+            colour::BLACK,
+            PatternType::Perturbed { scale, octaves: 3 },
+        )
+    }
+    /// Create a marble pattern with c1 as the base and c2 as the veins
+    pub fn new_marble(c1: impl Into<Paint>, c2: impl Into<Paint>) -> Pattern {
+        Pattern::new(
             c1,
             c2,
-            pattern_type: PatternType::Ring(),
-            transformation: identity(),
-        }
-    }
-    pub fn new_checkers(c1: colour::Colour, c2: colour::Colour) -> Pattern {
-        Pattern {
+            PatternType::Marble {
+                octaves: 5,
+                turbulence: 4.0,
+            },
+        )
+    }
+    /// Create a wood pattern with rings that fade from c1 to c2
+    pub fn new_wood(c1: impl Into<Paint>, c2: impl Into<Paint>) -> Pattern {
+        Pattern::new(
             c1,
             c2,
-            pattern_type: PatternType::Checkers(),
-            transformation: identity(),
-        }
+            PatternType::Wood {
+                octaves: 3,
+                turbulence: 0.3,
+            },
+        )
+    }
+    /// Create a cellular (Worley) pattern that blends from c1 to c2 as the selected distance grows
+    pub fn new_cellular(
+        c1: impl Into<Paint>,
+        c2: impl Into<Paint>,
+        metric: DistanceMetric,
+        output: CellularOutput,
+    ) -> Pattern {
+        Pattern::new(c1, c2, PatternType::Cellular { metric, output })
+    }
+    /// Create a pattern from a colour ramp
+    pub fn new_ramp(ramp: ColourRamp) -> Pattern {
+        Pattern::new(colour::BLACK, colour::WHITE, PatternType::Ramp(ramp))
+    }
+    /// Interpolate between the two slots at a point
+    fn lerp_slots(&self, point: Tuple, fraction: f32) -> Colour {
+        let c1 = self.c1.colour_at(point);
+        c1 + (self.c2.colour_at(point) - c1) * fraction
     }
     pub fn pattern_at(&self, point: Tuple) -> Colour {
-        match self.pattern_type {
-            PatternType::Test() => {
-                Colour::new(point.x, point.y, point.z)
-            }
+        match &self.pattern_type {
+            PatternType::Test() => Colour::new(point.x, point.y, point.z),
             PatternType::Striped() => {
                 if (point.x.floor().rem_euclid(2.0)) > 0.0 {
-                    self.c2
+                    self.c2.colour_at(point)
                 } else {
-                    self.c1
+                    self.c1.colour_at(point)
                 }
             }
             PatternType::Gradient() => {
-                let distance = self.c2 - self.c1;
+                let c1 = self.c1.colour_at(point);
+                let distance = self.c2.colour_at(point) - c1;
                 let fraction = point.x - point.x.floor();
-                self.c1 + distance * fraction
+                c1 + distance * fraction
             }
             PatternType::Ring() => {
                 if (((point.x.powi(2) + point.z.powi(2)).sqrt().floor()).rem_euclid(2.0)) == 0.0 {
-                    self.c1
+                    self.c1.colour_at(point)
                 } else {
-                    self.c2
+                    self.c2.colour_at(point)
                 }
             }
             PatternType::Checkers() => {
                 // Move the point slightly positive incase they are actually 0.0 but FP errors have them below 0
-                let point_x = (point.x + DEFAULT_EPSILON).floor(); 
-                let point_y = (point.y + DEFAULT_EPSILON).floor(); 
-                let point_z = (point.z + DEFAULT_EPSILON).floor(); 
+                let point_x = (point.x + DEFAULT_EPSILON).floor();
+                let point_y = (point.y + DEFAULT_EPSILON).floor();
+                let point_z = (point.z + DEFAULT_EPSILON).floor();
                 if (point_x + point_y + point_z).rem_euclid(2.0) == 0.0 {
-                    self.c1
+                    self.c1.colour_at(point)
                 } else {
-                    self.c2
+                    self.c2.colour_at(point)
                 }
             }
+            PatternType::Image(texture) => texture.colour_at(point),
+            PatternType::Blend() => (self.c1.colour_at(point) + self.c2.colour_at(point)) * 0.5,
+            PatternType::Mix(fraction) => self.lerp_slots(point, *fraction),
+            PatternType::Noise { octaves } => {
+                let fraction = (fbm(point, *octaves) + 1.0) / 2.0;
+                self.lerp_slots(point, fraction.clamp(0.0, 1.0))
+            }
+            PatternType::Perturbed { scale, octaves } => {
+                let jittered = point + fbm_vector(point, *octaves) * *scale;
+                self.c1.colour_at(jittered)
+            }
+            PatternType::Marble {
+                octaves,
+                turbulence: strength,
+            } => {
+                let phase = point.x + strength * turbulence(point, *octaves);
+                self.lerp_slots(point, 0.5 + 0.5 * (phase * PI).sin())
+            }
+            PatternType::Wood {
+                octaves,
+                turbulence: strength,
+            } => {
+                let radius =
+                    (point.x.powi(2) + point.z.powi(2)).sqrt() + strength * fbm(point, *octaves);
+                self.lerp_slots(point, radius - radius.floor())
+            }
+            PatternType::Cellular { metric, output } => {
+                let fraction = cellular(point, *metric, *output);
+                self.lerp_slots(point, fraction.clamp(0.0, 1.0))
+            }
+            PatternType::Ramp(ramp) => ramp.colour_at_point(point),
         }
     }
 }
@@ -210,7 +603,13 @@ mod tests {
     use approx::assert_relative_eq;
 
     use crate::{
-        colour::{Colour, BLACK, WHITE}, matrix::identity, shapes::{Material, Object, Pattern}, transformation::{scale, translation}, tuple::{point, vector}, DEFAULT_EPSILON
+        colour::{Colour, BLACK, BLUE, GREEN, RED, WHITE},
+        matrix::identity,
+        noise::{worley, CellularOutput, DistanceMetric},
+        shapes::{Glossy, Material, Object, Pattern, PatternType},
+        transformation::{scale, translation},
+        tuple::{point, vector},
+        DEFAULT_EPSILON,
     };
 
     #[test]
@@ -223,7 +622,7 @@ mod tests {
         let mut s = Object::new_sphere();
         let mut m = Material::new();
         m.ambient = 1.0;
-        s.material = m;
+        s.material = m.clone();
         assert_eq!(s.material, m);
     }
     #[test]
@@ -247,7 +646,7 @@ mod tests {
         let mut s = Object::new();
         let mut m = Material::new();
         m.ambient = 1.0;
-        s.material = m;
+        s.material = m.clone();
         assert_eq!(s.material, m);
     }
     #[test]
@@ -384,6 +783,24 @@ mod tests {
         assert_eq!(m.reflective, 0.0);
     }
     #[test]
+    fn default_material_is_not_emissive() {
+        let m = Material::new();
+        assert_eq!(m.emission(), BLACK);
+    }
+    #[test]
+    fn transmittance_follows_beer_lambert() {
+        let mut m = Material::new();
+        assert_eq!(m.transmittance(10.0), WHITE);
+        m.absorption_colour = Colour::new(1.0, 0.5, 0.0);
+        m.absorption_density = 2.0;
+        assert_eq!(m.transmittance(0.0), WHITE);
+        assert_relative_eq!(
+            m.transmittance(0.5),
+            Colour::new(1.0, f32::exp(-0.5), f32::exp(-1.0))
+        );
+        assert_eq!(m.transmittance(f32::INFINITY), Colour::new(1.0, 0.0, 0.0));
+    }
+    #[test]
     fn material_has_transparency_refractivity() {
         let m = Material::new();
         assert_eq!(m.transparency, 0.0);
@@ -401,6 +818,139 @@ mod tests {
         let mut s = Object::new_sphere();
         s.transform = scale(2.0, 2.0, 2.0);
         s.material.pattern = Some(Pattern::new_test());
-        assert_eq!(s.pattern_at(point(2.0, 3.0, 4.0)), Colour::new(1.0, 1.5, 2.0));
+        assert_eq!(
+            s.pattern_at(point(2.0, 3.0, 4.0)),
+            Colour::new(1.0, 1.5, 2.0)
+        );
+    }
+    #[test]
+    fn stripes_of_checkers() {
+        let mut red_checks = Pattern::new_checkers(RED, WHITE);
+        red_checks.transformation = scale(0.5, 0.5, 0.5);
+        let blue_checks = Pattern::new_checkers(BLUE, GREEN);
+        let p = Pattern::new_striped(red_checks, blue_checks);
+        assert_eq!(p.pattern_at(point(0.25, 0.0, 0.0)), RED);
+        assert_eq!(p.pattern_at(point(0.75, 0.0, 0.0)), WHITE);
+        assert_eq!(p.pattern_at(point(1.5, 0.0, 0.0)), GREEN);
+        assert_eq!(p.pattern_at(point(1.5, 0.0, 1.5)), BLUE);
+    }
+    #[test]
+    fn nested_pattern_inside_object() {
+        let mut o = Object::new_sphere();
+        o.transform = scale(2.0, 2.0, 2.0);
+        let mut inner = Pattern::new_striped(RED, BLUE);
+        inner.transformation = translation(0.5, 0.0, 0.0);
+        o.material.pattern = Some(Pattern::new_ring(inner, WHITE));
+        assert_eq!(o.pattern_at(point(0.5, 0.0, 0.0)), BLUE);
+        assert_eq!(o.pattern_at(point(1.5, 0.0, 0.0)), RED);
+        assert_eq!(o.pattern_at(point(2.5, 0.0, 0.0)), WHITE);
+    }
+    #[test]
+    fn blend_averages_patterns() {
+        let p = Pattern::new_blend(Pattern::new_striped(WHITE, BLACK), RED);
+        assert_eq!(
+            p.pattern_at(point(0.0, 0.0, 0.0)),
+            Colour::new(1.0, 0.5, 0.5)
+        );
+        assert_eq!(
+            p.pattern_at(point(1.0, 0.0, 0.0)),
+            Colour::new(0.5, 0.0, 0.0)
+        );
+    }
+    #[test]
+    fn mix_interpolates_patterns() {
+        let p = Pattern::new_mix(WHITE, Pattern::new_checkers(BLACK, BLUE), 0.25);
+        assert_eq!(
+            p.pattern_at(point(0.0, 0.0, 0.0)),
+            Colour::new(0.75, 0.75, 0.75)
+        );
+        assert_eq!(
+            p.pattern_at(point(1.0, 0.0, 0.0)),
+            Colour::new(0.75, 0.75, 1.0)
+        );
+    }
+    #[test]
+    fn noise_pattern_stays_between_slots() {
+        let p = Pattern::new_noise(BLACK, WHITE);
+        assert_eq!(
+            p.pattern_at(point(0.0, 0.0, 0.0)),
+            Colour::new(0.5, 0.5, 0.5)
+        );
+        for i in 0..50 {
+            let f = i as f32 * 0.31;
+            let c = p.pattern_at(point(f, f * 0.5, -f));
+            assert!(c.red >= 0.0 && c.red <= 1.0);
+        }
+    }
+    #[test]
+    fn perturbed_pattern_jitters_points() {
+        let p = Pattern::new_perturbed(Pattern::new_test(), 0.5);
+        let point = point(0.3, 0.7, 0.2);
+        let c = p.pattern_at(point);
+        assert!(c != Colour::new(0.3, 0.7, 0.2));
+        assert!((c.red - 0.3).abs() <= 1.0 && (c.green - 0.7).abs() <= 1.0);
+        let unperturbed = Pattern::new_perturbed(Pattern::new_test(), 0.0);
+        assert_eq!(unperturbed.pattern_at(point), Colour::new(0.3, 0.7, 0.2));
+    }
+    #[test]
+    fn marble_and_wood_stay_between_slots() {
+        let marble = Pattern::new_marble(WHITE, BLACK);
+        let wood = Pattern::new_wood(WHITE, BLACK);
+        for i in 0..50 {
+            let f = i as f32 * 0.23;
+            let p = point(f, f * 0.3, f * 0.7);
+            let m = marble.pattern_at(p);
+            let w = wood.pattern_at(p);
+            assert!(m.red >= 0.0 && m.red <= 1.0);
+            assert!(w.red >= 0.0 && w.red <= 1.0);
+        }
+    }
+    #[test]
+    fn wood_rings_without_turbulence() {
+        let mut wood = Pattern::new_wood(BLACK, WHITE);
+        wood.pattern_type = PatternType::Wood {
+            octaves: 3,
+            turbulence: 0.0,
+        };
+        assert_eq!(wood.pattern_at(point(0.0, 0.0, 0.0)), BLACK);
+        assert_eq!(
+            wood.pattern_at(point(0.0, 0.0, 1.5)),
+            Colour::new(0.5, 0.5, 0.5)
+        );
+        assert_eq!(
+            wood.pattern_at(point(0.3, 0.0, 0.4)),
+            Colour::new(0.5, 0.5, 0.5)
+        );
+    }
+    #[test]
+    fn cellular_pattern_maps_distance_to_colour() {
+        let p = Pattern::new_cellular(BLACK, WHITE, DistanceMetric::Euclidean, CellularOutput::F1);
+        let (f1, _) = worley(point(0.4, 1.3, -2.6), DistanceMetric::Euclidean);
+        assert_eq!(p.pattern_at(point(0.4, 1.3, -2.6)), Colour::new(f1, f1, f1));
+        let cracks = Pattern::new_cellular(
+            BLACK,
+            WHITE,
+            DistanceMetric::Manhattan,
+            CellularOutput::F2MinusF1,
+        );
+        let c = cracks.pattern_at(point(3.1, 0.2, 0.7));
+        assert!(c.red >= 0.0 && c.red <= 1.0);
+    }
+    #[test]
+    fn glossy_scatter_stays_on_side_of_surface() {
+        let glossy = Glossy::new(1.0, 1);
+        let normal = vector(0.0, 1.0, 0.0);
+        let grazing = vector(1.0, 0.05, 0.0).normalize();
+        for _ in 0..500 {
+            assert!(glossy.scatter(grazing, normal).y > 0.0);
+            assert!(glossy.scatter(-grazing, normal).y < 0.0);
+        }
+        assert_eq!(
+            Glossy::new(2.0, 0),
+            Glossy {
+                roughness: 1.0,
+                samples: 1
+            }
+        );
     }
 }
@@ -0,0 +1,214 @@
+use std::f32::consts::FRAC_PI_2;
+
+use crate::{
+    colour::{Colour, BLACK},
+    ray::Light,
+    tuple::{point, vector, Tuple},
+};
+
+/// How far away the sun light is placed, far enough that its rays are close to parallel.
+const SUN_DISTANCE: f32 = 10000.0;
+/// Wavelengths in micrometres used for the red, green and blue channels of the sun.
+const WAVELENGTHS: [f32; 3] = [0.65, 0.55, 0.45];
+
+/// The Preetham analytic model of daylight, a sky dome lit by a sun and the light of the sun after it crosses the
+/// atmosphere. The sun's position is given by its elevation above the horizon and its azimuth, measured from the
+/// +z axis towards the +x axis, both in radians. Turbidity is the haziness of the air, 2.0 is clear and 10.0 hazy.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct PhysicalSky {
+    pub sun_elevation: f32,
+    pub sun_azimuth: f32,
+    pub turbidity: f32,
+    /// Scales the sky luminance, in thousands of candela per square metre, to colour values.
+    pub exposure: f32,
+    /// The brightness of the sun light before the atmosphere filters it.
+    pub sun_intensity: f32,
+    /// Perez distribution coefficients for luminance and the x and y chromaticities.
+    perez: [[f32; 5]; 3],
+    /// Luminance and chromaticity looking straight up.
+    zenith: [f32; 3],
+}
+impl PhysicalSky {
+    /// Create a sky from the sun elevation and azimuth in radians and the turbidity
+    pub fn new(sun_elevation: f32, sun_azimuth: f32, turbidity: f32) -> PhysicalSky {
+        let t = turbidity.max(1.0);
+        let perez = [
+            [
+                0.1787 * t - 1.4630,
+                -0.3554 * t + 0.4275,
+                -0.0227 * t + 5.3251,
+                0.1206 * t - 2.5771,
+                -0.0670 * t + 0.3703,
+            ],
+            [
+                -0.0193 * t - 0.2592,
+                -0.0665 * t + 0.0008,
+                -0.0004 * t + 0.2125,
+                -0.0641 * t - 0.8989,
+                -0.0033 * t + 0.0452,
+            ],
+            [
+                -0.0167 * t - 0.2608,
+                -0.0950 * t + 0.0092,
+                -0.0079 * t + 0.2102,
+                -0.0441 * t - 1.6537,
+                -0.0109 * t + 0.0529,
+            ],
+        ];
+        let theta = FRAC_PI_2 - sun_elevation.clamp(0.0, FRAC_PI_2);
+        let chi = (4.0 / 9.0 - t / 120.0) * (std::f32::consts::PI - 2.0 * theta);
+        let luminance = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+        let (t2, th2, th3) = (t * t, theta * theta, theta * theta * theta);
+        let x = t2 * (0.00166 * th3 - 0.00375 * th2 + 0.00209 * theta)
+            + t * (-0.02903 * th3 + 0.06377 * th2 - 0.03202 * theta + 0.00394)
+            + (0.11693 * th3 - 0.21196 * th2 + 0.06052 * theta + 0.25886);
+        let y = t2 * (0.00275 * th3 - 0.00610 * th2 + 0.00317 * theta)
+            + t * (-0.04214 * th3 + 0.08970 * th2 - 0.04153 * theta + 0.00516)
+            + (0.15346 * th3 - 0.26756 * th2 + 0.06670 * theta + 0.26688);
+        PhysicalSky {
+            sun_elevation,
+            sun_azimuth,
+            turbidity: t,
+            exposure: 0.1,
+            sun_intensity: 1.0,
+            perez,
+            zenith: [luminance.max(0.0), x, y],
+        }
+    }
+    /// The direction towards the sun
+    pub fn sun_direction(&self) -> Tuple {
+        let (sin_e, cos_e) = self.sun_elevation.sin_cos();
+        let (sin_a, cos_a) = self.sun_azimuth.sin_cos();
+        vector(cos_e * sin_a, sin_e, cos_e * cos_a)
+    }
+    /// The luminance looking straight up, before exposure
+    pub fn zenith_luminance(&self) -> f32 {
+        self.zenith[0]
+    }
+    /// The colour of the sky looking along direction, directions below the horizon see the horizon
+    pub fn colour_at(&self, direction: Tuple) -> Colour {
+        let d = direction.normalize();
+        let sun = self.sun_direction();
+        let cos_theta = d.y.max(0.01);
+        let gamma = d.dot(sun).clamp(-1.0, 1.0).acos();
+        let theta_sun = FRAC_PI_2 - self.sun_elevation.clamp(0.0, FRAC_PI_2);
+        let mut yxy = [0.0; 3];
+        for (i, value) in yxy.iter_mut().enumerate() {
+            let c = self.perez[i];
+            *value = self.zenith[i] * perez(c, cos_theta, gamma) / perez(c, 1.0, theta_sun);
+        }
+        let [luminance, x, y] = yxy;
+        let luminance = luminance * self.exposure;
+        xyy_to_rgb(x, y, luminance)
+    }
+    /// A light far away in the direction of the sun, coloured by the air it passes through on the way down
+    pub fn sun_light(&self) -> Light {
+        let position = point(0.0, 0.0, 0.0) + self.sun_direction() * SUN_DISTANCE;
+        if self.sun_elevation <= 0.0 {
+            return Light::new(position, BLACK);
+        }
+        let zenith_degrees = 90.0 - self.sun_elevation.to_degrees();
+        // Relative length of the path through the air compared to straight down
+        let mass = 1.0 / (self.sun_elevation.sin() + 0.15 * (93.885 - zenith_degrees).powf(-1.253));
+        let beta = 0.04608 * self.turbidity - 0.04586;
+        let channel = |wavelength: f32| {
+            let rayleigh = (-0.008735 * wavelength.powf(-4.08) * mass).exp();
+            let aerosol = (-beta * wavelength.powf(-1.3) * mass).exp();
+            self.sun_intensity * rayleigh * aerosol
+        };
+        Light::new(
+            position,
+            Colour::new(
+                channel(WAVELENGTHS[0]),
+                channel(WAVELENGTHS[1]),
+                channel(WAVELENGTHS[2]),
+            ),
+        )
+    }
+}
+/// The Perez sky distribution for a direction cos_theta from the zenith and gamma from the sun
+fn perez(c: [f32; 5], cos_theta: f32, gamma: f32) -> f32 {
+    (1.0 + c[0] * (c[1] / cos_theta).exp())
+        * (1.0 + c[2] * (c[3] * gamma).exp() + c[4] * gamma.cos().powi(2))
+}
+/// Convert a chromaticity and luminance to linear sRGB
+fn xyy_to_rgb(x: f32, y: f32, luminance: f32) -> Colour {
+    if y <= 0.0 {
+        return BLACK;
+    }
+    let big_x = x / y * luminance;
+    let big_z = (1.0 - x - y) / y * luminance;
+    Colour::new(
+        (3.2406 * big_x - 1.5372 * luminance - 0.4986 * big_z).max(0.0),
+        (-0.9689 * big_x + 1.8758 * luminance + 0.0415 * big_z).max(0.0),
+        (0.0557 * big_x - 0.2040 * luminance + 1.0570 * big_z).max(0.0),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::{FRAC_PI_2, FRAC_PI_4, PI};
+
+    use approx::assert_relative_eq;
+
+    use super::PhysicalSky;
+    use crate::{colour::BLACK, environment::luminance, tuple::vector, DEFAULT_EPSILON};
+
+    #[test]
+    fn sun_direction_from_angles() {
+        let sky = PhysicalSky::new(FRAC_PI_2, 0.0, 3.0);
+        assert_relative_eq!(
+            sky.sun_direction(),
+            vector(0.0, 1.0, 0.0),
+            epsilon = DEFAULT_EPSILON
+        );
+        let sky = PhysicalSky::new(0.0, FRAC_PI_2, 3.0);
+        assert_relative_eq!(
+            sky.sun_direction(),
+            vector(1.0, 0.0, 0.0),
+            epsilon = DEFAULT_EPSILON
+        );
+        let sky = PhysicalSky::new(FRAC_PI_4, PI, 3.0);
+        assert_relative_eq!(
+            sky.sun_direction(),
+            vector(0.0, f32::sqrt(2.0) / 2.0, -f32::sqrt(2.0) / 2.0),
+            epsilon = DEFAULT_EPSILON
+        );
+    }
+    #[test]
+    fn zenith_matches_model_luminance() {
+        let sky = PhysicalSky::new(1.0, 0.3, 2.5);
+        let zenith = sky.colour_at(vector(0.0, 1.0, 0.0));
+        assert_relative_eq!(
+            luminance(zenith),
+            sky.zenith_luminance() * sky.exposure,
+            epsilon = 0.01
+        );
+    }
+    #[test]
+    fn clear_day_sky_is_blue() {
+        let sky = PhysicalSky::new(1.0, 0.0, 2.5);
+        let c = sky.colour_at(vector(0.0, 1.0, -0.5));
+        assert!(c.blue > c.red);
+        // Brighter close to the sun
+        let near_sun = sky.colour_at(sky.sun_direction() + vector(0.0, 0.0, 0.1));
+        assert!(luminance(near_sun) > luminance(c));
+    }
+    #[test]
+    fn low_sun_is_redder_and_dimmer() {
+        let noon = PhysicalSky::new(1.3, 0.0, 3.0).sun_light().intensity;
+        let evening = PhysicalSky::new(0.05, 0.0, 3.0).sun_light().intensity;
+        assert!(evening.red / evening.blue > noon.red / noon.blue);
+        assert!(luminance(evening) < luminance(noon));
+        assert_eq!(
+            PhysicalSky::new(-0.1, 0.0, 3.0).sun_light().intensity,
+            BLACK
+        );
+    }
+    #[test]
+    fn hazier_sky_dims_sun() {
+        let clear = PhysicalSky::new(0.5, 0.0, 2.0).sun_light().intensity;
+        let hazy = PhysicalSky::new(0.5, 0.0, 8.0).sun_light().intensity;
+        assert!(luminance(hazy) < luminance(clear));
+    }
+}
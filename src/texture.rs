@@ -0,0 +1,412 @@
+use std::{f32::consts::PI, sync::Arc};
+
+use crate::{
+    canvas::Canvas,
+    colour::{Colour, BLACK},
+    tuple::Tuple,
+};
+
+/// The ways a point in pattern space can be turned into UV texture coordinates.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum UvMapping {
+    /// Wrap the texture around a unit sphere, u follows the longitude and v the latitude.
+    Spherical,
+    /// Tile the texture over the xz-plane, repeating every unit.
+    Planar,
+    /// Wrap the texture around a cylinder on the y axis, repeating every unit along y.
+    Cylindrical,
+    /// Project the texture onto the faces of a cube from -1 to 1. The image is laid out as a horizontal cross.
+    Cube,
+    /// Stretch the texture over the square around a disc on the xz-plane.
+    Disc { radius: f32 },
+    /// Wrap the texture around a flat ring on the xz-plane, u follows the angle and v runs from inner to outer.
+    Annulus { inner: f32, outer: f32 },
+    /// Stretch the texture once over a rectangle on the xz-plane centred on the origin.
+    Rectangle { width: f32, depth: f32 },
+}
+impl UvMapping {
+    /// Map a point to (u, v) coordinates between 0.0 and 1.0
+    pub fn map(&self, point: Tuple) -> (f32, f32) {
+        match *self {
+            UvMapping::Spherical => spherical_map(point),
+            UvMapping::Planar => planar_map(point),
+            UvMapping::Cylindrical => cylindrical_map(point),
+            UvMapping::Cube => cube_map(point),
+            UvMapping::Disc { radius } => rectangle_map(point, 2.0 * radius, 2.0 * radius),
+            UvMapping::Annulus { inner, outer } => annulus_map(point, inner, outer),
+            UvMapping::Rectangle { width, depth } => rectangle_map(point, width, depth),
+        }
+    }
+    /// Whether the texture repeats along u and v, so its opposite edges meet
+    pub fn repeats(&self) -> (bool, bool) {
+        match *self {
+            UvMapping::Spherical | UvMapping::Annulus { .. } => (true, false),
+            UvMapping::Planar | UvMapping::Cylindrical => (true, true),
+            UvMapping::Cube | UvMapping::Disc { .. } | UvMapping::Rectangle { .. } => {
+                (false, false)
+            }
+        }
+    }
+}
+/// How pixels are read from an image texture.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum TextureFilter {
+    /// Use the colour of the closest pixel.
+    Nearest,
+    /// Blend the four closest pixels.
+    Bilinear,
+}
+/// An image that is sampled through a UV mapping.
+#[derive(Debug, Clone)]
+pub struct ImageTexture {
+    pub image: Arc<Canvas>,
+    pub mapping: UvMapping,
+    pub filter: TextureFilter,
+}
+impl ImageTexture {
+    /// Create a new image texture
+    pub fn new(image: Arc<Canvas>, mapping: UvMapping, filter: TextureFilter) -> ImageTexture {
+        ImageTexture {
+            image,
+            mapping,
+            filter,
+        }
+    }
+    /// Find the colour of the texture at a point in pattern space
+    pub fn colour_at(&self, point: Tuple) -> Colour {
+        let (u, v) = self.mapping.map(point);
+        sample_repeating(&self.image, u, v, self.filter, self.mapping.repeats())
+    }
+}
+/// Textures are equal when they read the same shared image, not merely an identical copy of it,
+/// through the same mapping and filter.
+impl PartialEq for ImageTexture {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.image, &other.image)
+            && self.mapping == other.mapping
+            && self.filter == other.filter
+    }
+}
+/// Sample an image at (u, v), where (0, 0) is the bottom left corner and (1, 1) the top right.
+pub fn sample(image: &Canvas, u: f32, v: f32, filter: TextureFilter) -> Colour {
+    sample_repeating(image, u, v, filter, (false, false))
+}
+/// Sample an image at (u, v) like `sample`, blending the pixels on opposite edges along the axes that repeat.
+/// Empty images are black.
+pub fn sample_repeating(
+    image: &Canvas,
+    u: f32,
+    v: f32,
+    filter: TextureFilter,
+    (repeat_u, repeat_v): (bool, bool),
+) -> Colour {
+    if image.get_width() == 0 || image.get_height() == 0 {
+        return BLACK;
+    }
+    let (x0, x1, fx) = neighbours(u, image.get_width(), repeat_u);
+    let (y0, y1, fy) = neighbours(1.0 - v, image.get_height(), repeat_v);
+    match filter {
+        TextureFilter::Nearest => {
+            let x = if fx < 0.5 { x0 } else { x1 };
+            let y = if fy < 0.5 { y0 } else { y1 };
+            image.pixel_at(x, y)
+        }
+        TextureFilter::Bilinear => {
+            let top = image.pixel_at(x0, y0) * (1.0 - fx) + image.pixel_at(x1, y0) * fx;
+            let bottom = image.pixel_at(x0, y1) * (1.0 - fx) + image.pixel_at(x1, y1) * fx;
+            top * (1.0 - fy) + bottom * fy
+        }
+    }
+}
+/// The pixels either side of a coordinate from 0.0 to 1.0 along an axis of size pixels, and how far it lies
+/// from the first towards the second. Repeating axes space the pixel centres evenly so the last pixel meets the
+/// first, otherwise the first and last pixels sit on the edges.
+fn neighbours(coord: f32, size: usize, repeats: bool) -> (usize, usize, f32) {
+    if repeats {
+        let x = coord * size as f32 - 0.5;
+        let x0 = x.floor();
+        let first = (x0 as i64).rem_euclid(size as i64) as usize;
+        (first, (first + 1) % size, x - x0)
+    } else {
+        let max = size - 1;
+        let x = coord.clamp(0.0, 1.0) * max as f32;
+        let x0 = x.floor() as usize;
+        (x0, (x0 + 1).min(max), x - x0 as f32)
+    }
+}
+/// Map a point on a sphere to (u, v)
+pub fn spherical_map(point: Tuple) -> (f32, f32) {
+    let theta = point.x.atan2(point.z);
+    let radius = (point.x.powi(2) + point.y.powi(2) + point.z.powi(2)).sqrt();
+    let phi = if radius > 0.0 {
+        (point.y / radius).clamp(-1.0, 1.0).acos()
+    } else {
+        0.0
+    };
+    let raw_u = theta / (2.0 * PI);
+    let u = 1.0 - (raw_u + 0.5);
+    let v = 1.0 - phi / PI;
+    (u, v)
+}
+/// Map a point on the xz-plane to (u, v)
+pub fn planar_map(point: Tuple) -> (f32, f32) {
+    (point.x.rem_euclid(1.0), point.z.rem_euclid(1.0))
+}
+/// Map a point on a cylinder around the y axis to (u, v)
+pub fn cylindrical_map(point: Tuple) -> (f32, f32) {
+    let theta = point.x.atan2(point.z);
+    let raw_u = theta / (2.0 * PI);
+    let u = 1.0 - (raw_u + 0.5);
+    (u, point.y.rem_euclid(1.0))
+}
+/// Map a point on a rectangle on the xz-plane centred on the origin to (u, v)
+pub fn rectangle_map(point: Tuple, width: f32, depth: f32) -> (f32, f32) {
+    (
+        (point.x / width + 0.5).clamp(0.0, 1.0),
+        (point.z / depth + 0.5).clamp(0.0, 1.0),
+    )
+}
+/// Map a point on a ring on the xz-plane to (u, v)
+pub fn annulus_map(point: Tuple, inner: f32, outer: f32) -> (f32, f32) {
+    let (u, _) = cylindrical_map(point);
+    let distance = (point.x.powi(2) + point.z.powi(2)).sqrt();
+    (u, ((distance - inner) / (outer - inner)).clamp(0.0, 1.0))
+}
+/// The faces of a cube, used by the cube mapping.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum CubeFace {
+    Left,
+    Right,
+    Front,
+    Back,
+    Up,
+    Down,
+}
+/// Find which face of a cube a point lies on
+pub fn cube_face(point: Tuple) -> CubeFace {
+    let abs_x = point.x.abs();
+    let abs_y = point.y.abs();
+    let abs_z = point.z.abs();
+    let coord = abs_x.max(abs_y).max(abs_z);
+    if coord == point.x {
+        CubeFace::Right
+    } else if coord == -point.x {
+        CubeFace::Left
+    } else if coord == point.y {
+        CubeFace::Up
+    } else if coord == -point.y {
+        CubeFace::Down
+    } else if coord == point.z {
+        CubeFace::Front
+    } else {
+        CubeFace::Back
+    }
+}
+/// Map a point on a cube to (u, v) on one of its faces
+pub fn cube_face_uv(face: CubeFace, point: Tuple) -> (f32, f32) {
+    match face {
+        CubeFace::Front => (
+            ((point.x + 1.0).rem_euclid(2.0)) / 2.0,
+            ((point.y + 1.0).rem_euclid(2.0)) / 2.0,
+        ),
+        CubeFace::Back => (
+            ((1.0 - point.x).rem_euclid(2.0)) / 2.0,
+            ((point.y + 1.0).rem_euclid(2.0)) / 2.0,
+        ),
+        CubeFace::Left => (
+            ((point.z + 1.0).rem_euclid(2.0)) / 2.0,
+            ((point.y + 1.0).rem_euclid(2.0)) / 2.0,
+        ),
+        CubeFace::Right => (
+            ((1.0 - point.z).rem_euclid(2.0)) / 2.0,
+            ((point.y + 1.0).rem_euclid(2.0)) / 2.0,
+        ),
+        CubeFace::Up => (
+            ((point.x + 1.0).rem_euclid(2.0)) / 2.0,
+            ((1.0 - point.z).rem_euclid(2.0)) / 2.0,
+        ),
+        CubeFace::Down => (
+            ((point.x + 1.0).rem_euclid(2.0)) / 2.0,
+            ((point.z + 1.0).rem_euclid(2.0)) / 2.0,
+        ),
+    }
+}
+/// Map a point on a cube to (u, v) in a horizontal cross layout.
+/// The middle row holds the left, front, right and back faces with up above and down below the front face.
+pub fn cube_map(point: Tuple) -> (f32, f32) {
+    let face = cube_face(point);
+    let (u, v) = cube_face_uv(face, point);
+    let (column, row) = match face {
+        CubeFace::Left => (0.0, 1.0),
+        CubeFace::Front => (1.0, 1.0),
+        CubeFace::Right => (2.0, 1.0),
+        CubeFace::Back => (3.0, 1.0),
+        CubeFace::Up => (1.0, 2.0),
+        CubeFace::Down => (1.0, 0.0),
+    };
+    ((column + u) / 4.0, (row + v) / 3.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{f32::consts::FRAC_1_SQRT_2, sync::Arc};
+
+    use approx::assert_relative_eq;
+
+    use super::{
+        annulus_map, cube_face, cube_face_uv, cube_map, cylindrical_map, planar_map, rectangle_map,
+        sample, sample_repeating, spherical_map, CubeFace, TextureFilter,
+    };
+    use crate::{
+        canvas::Canvas,
+        colour::{Colour, BLACK, WHITE},
+        shapes::{Object, Pattern},
+        texture::UvMapping,
+        tuple::point,
+        DEFAULT_EPSILON,
+    };
+
+    #[test]
+    fn spherical_mapping() {
+        let cases = [
+            (point(0.0, 0.0, -1.0), (0.0, 0.5)),
+            (point(1.0, 0.0, 0.0), (0.25, 0.5)),
+            (point(0.0, 0.0, 1.0), (0.5, 0.5)),
+            (point(-1.0, 0.0, 0.0), (0.75, 0.5)),
+            (point(0.0, 1.0, 0.0), (0.5, 1.0)),
+            (point(0.0, -1.0, 0.0), (0.5, 0.0)),
+            (point(FRAC_1_SQRT_2, FRAC_1_SQRT_2, 0.0), (0.25, 0.75)),
+        ];
+        for (p, (u, v)) in cases {
+            let (mu, mv) = spherical_map(p);
+            assert_relative_eq!(mu, u, epsilon = DEFAULT_EPSILON);
+            assert_relative_eq!(mv, v, epsilon = DEFAULT_EPSILON);
+        }
+    }
+    #[test]
+    fn planar_mapping() {
+        assert_eq!(planar_map(point(0.25, 0.0, 0.5)), (0.25, 0.5));
+        assert_eq!(planar_map(point(0.25, 0.0, -0.25)), (0.25, 0.75));
+        assert_eq!(planar_map(point(1.25, 0.5, -1.5)), (0.25, 0.5));
+    }
+    #[test]
+    fn flat_shape_mappings() {
+        assert_eq!(rectangle_map(point(0.0, 0.0, 0.0), 4.0, 2.0), (0.5, 0.5));
+        assert_eq!(rectangle_map(point(-2.0, 0.0, 0.5), 4.0, 2.0), (0.0, 0.75));
+        let (u, v) = annulus_map(point(0.0, 0.0, -1.5), 1.0, 2.0);
+        assert_relative_eq!(u, 0.0);
+        assert_relative_eq!(v, 0.5);
+        let (u, v) = annulus_map(point(2.0, 0.0, 0.0), 1.0, 2.0);
+        assert_relative_eq!(u, 0.25);
+        assert_relative_eq!(v, 1.0);
+        assert_eq!(
+            UvMapping::Disc { radius: 2.0 }.map(point(1.0, 0.0, -2.0)),
+            (0.75, 0.0)
+        );
+    }
+    #[test]
+    fn cylindrical_mapping() {
+        let (u, v) = cylindrical_map(point(0.0, 0.0, -1.0));
+        assert_relative_eq!(u, 0.0);
+        assert_relative_eq!(v, 0.0);
+        let (u, v) = cylindrical_map(point(0.0, 0.5, -1.0));
+        assert_relative_eq!(u, 0.0);
+        assert_relative_eq!(v, 0.5);
+        let (u, v) = cylindrical_map(point(FRAC_1_SQRT_2, 0.25, FRAC_1_SQRT_2));
+        assert_relative_eq!(u, 0.375, epsilon = DEFAULT_EPSILON);
+        assert_relative_eq!(v, 0.25);
+    }
+    #[test]
+    fn identify_cube_face() {
+        assert_eq!(cube_face(point(-1.0, 0.5, -0.25)), CubeFace::Left);
+        assert_eq!(cube_face(point(1.1, -0.75, 0.8)), CubeFace::Right);
+        assert_eq!(cube_face(point(0.1, 0.6, 0.9)), CubeFace::Front);
+        assert_eq!(cube_face(point(-0.7, 0.0, -2.0)), CubeFace::Back);
+        assert_eq!(cube_face(point(0.5, 1.0, 0.9)), CubeFace::Up);
+        assert_eq!(cube_face(point(-0.2, -1.3, 1.1)), CubeFace::Down);
+    }
+    #[test]
+    fn cube_face_mapping() {
+        assert_eq!(
+            cube_face_uv(CubeFace::Front, point(-0.5, 0.5, 1.0)),
+            (0.25, 0.75)
+        );
+        assert_eq!(
+            cube_face_uv(CubeFace::Back, point(0.5, 0.5, -1.0)),
+            (0.25, 0.75)
+        );
+        assert_eq!(
+            cube_face_uv(CubeFace::Left, point(-1.0, 0.5, -0.5)),
+            (0.25, 0.75)
+        );
+        assert_eq!(
+            cube_face_uv(CubeFace::Right, point(1.0, 0.5, 0.5)),
+            (0.25, 0.75)
+        );
+        assert_eq!(
+            cube_face_uv(CubeFace::Up, point(-0.5, 1.0, -0.5)),
+            (0.25, 0.75)
+        );
+        assert_eq!(
+            cube_face_uv(CubeFace::Down, point(-0.5, -1.0, 0.5)),
+            (0.25, 0.75)
+        );
+    }
+    #[test]
+    fn cube_mapping_uses_cross_layout() {
+        let (u, v) = cube_map(point(0.0, 0.0, 1.0));
+        assert_relative_eq!(u, 0.375);
+        assert_relative_eq!(v, 0.5);
+        let (u, v) = cube_map(point(0.0, 1.0, 0.0));
+        assert_relative_eq!(u, 0.375);
+        assert_relative_eq!(v, 2.5 / 3.0);
+    }
+    #[test]
+    fn sample_nearest_and_bilinear() {
+        let mut image = Canvas::new(2, 2, BLACK);
+        image.write_pixel(1, 0, WHITE);
+        assert_eq!(sample(&image, 1.0, 1.0, TextureFilter::Nearest), WHITE);
+        assert_eq!(sample(&image, 0.0, 1.0, TextureFilter::Nearest), BLACK);
+        assert_eq!(
+            sample(&image, 0.5, 1.0, TextureFilter::Bilinear),
+            Colour::new(0.5, 0.5, 0.5)
+        );
+        assert_eq!(
+            sample(&image, 0.5, 0.5, TextureFilter::Bilinear),
+            Colour::new(0.25, 0.25, 0.25)
+        );
+    }
+    #[test]
+    fn sample_empty_image() {
+        let image = Canvas::new(0, 0, WHITE);
+        assert_eq!(sample(&image, 0.5, 0.5, TextureFilter::Bilinear), BLACK);
+        assert_eq!(sample(&image, 0.5, 0.5, TextureFilter::Nearest), BLACK);
+    }
+    #[test]
+    fn repeating_sample_blends_across_edges() {
+        let mut image = Canvas::new(2, 1, BLACK);
+        image.write_pixel(1, 0, WHITE);
+        let half = Colour::new(0.5, 0.5, 0.5);
+        let repeat = (true, true);
+        let filter = TextureFilter::Bilinear;
+        // Either side of the seam lands halfway between the last and first pixels
+        assert_relative_eq!(sample_repeating(&image, 0.0, 0.5, filter, repeat), half);
+        assert_relative_eq!(sample_repeating(&image, 1.0, 0.5, filter, repeat), half);
+        assert_relative_eq!(sample_repeating(&image, 0.75, 0.5, filter, repeat), WHITE);
+        assert_relative_eq!(sample_repeating(&image, 0.25, 0.5, filter, repeat), BLACK);
+        assert_eq!(sample(&image, 1.0, 0.5, filter), WHITE);
+    }
+    #[test]
+    fn image_pattern_on_sphere() {
+        let mut image = Canvas::new(4, 2, BLACK);
+        image.write_pixel(2, 0, WHITE);
+        let mut s = Object::new_sphere();
+        s.material.pattern = Some(Pattern::new_image(
+            Arc::new(image),
+            UvMapping::Spherical,
+            TextureFilter::Nearest,
+        ));
+        assert_eq!(s.pattern_at(point(0.0, 0.0, 1.0)), BLACK);
+        assert_eq!(s.pattern_at(point(-0.8, 0.9, 0.1)), WHITE);
+    }
+}
@@ -1,4 +1,4 @@
-use std::{ops, fmt};
+use std::{fmt, ops};
 #[derive(Debug, Clone, Copy, PartialEq)]
 /// A tuple struct. Can be used to represent points and vectors.
 pub struct Tuple {
@@ -34,8 +34,8 @@ impl Tuple {
     /// Return a normalized tuple
     pub fn normalize(&self) -> Tuple {
         let mag = self.magnitude();
-        if mag > 0.0 {            
-            *self/mag
+        if mag > 0.0 {
+            *self / mag
         } else {
             *self
         }
@@ -59,7 +59,7 @@ impl Tuple {
     }
     /// Reflect a vector around normal
     pub fn reflect(self, normal: Tuple) -> Tuple {
-        self - normal*2.0*self.dot(normal)
+        self - normal * 2.0 * self.dot(normal)
     }
 }
 impl ops::Add for Tuple {
@@ -131,33 +131,37 @@ impl approx::AbsDiffEq for Tuple {
         f32::default_epsilon()
     }
     fn abs_diff_eq(&self, other: &Self, epsilon: Self::Epsilon) -> bool {
-        f32::abs_diff_eq(&self.x, &other.x, epsilon) &&
-        f32::abs_diff_eq(&self.y, &other.y, epsilon) &&
-        f32::abs_diff_eq(&self.z, &other.z, epsilon) &&
-        f32::abs_diff_eq(&self.w, &other.w, epsilon)
+        f32::abs_diff_eq(&self.x, &other.x, epsilon)
+            && f32::abs_diff_eq(&self.y, &other.y, epsilon)
+            && f32::abs_diff_eq(&self.z, &other.z, epsilon)
+            && f32::abs_diff_eq(&self.w, &other.w, epsilon)
     }
 
     fn abs_diff_ne(&self, other: &Self, epsilon: Self::Epsilon) -> bool {
         !Self::abs_diff_eq(self, other, epsilon)
     }
 }
-impl approx::RelativeEq for Tuple{
+impl approx::RelativeEq for Tuple {
     fn default_max_relative() -> Self::Epsilon {
         f32::default_max_relative()
     }
 
-    fn relative_eq(&self, other: &Self, epsilon: Self::Epsilon, max_relative: Self::Epsilon)
-            -> bool {
-        f32::relative_eq(&self.x, &other.x, epsilon, max_relative) &&
-        f32::relative_eq(&self.y, &other.y, epsilon, max_relative) &&
-        f32::relative_eq(&self.z, &other.z, epsilon, max_relative) &&
-        f32::relative_eq(&self.w, &other.w, epsilon, max_relative)        
+    fn relative_eq(
+        &self,
+        other: &Self,
+        epsilon: Self::Epsilon,
+        max_relative: Self::Epsilon,
+    ) -> bool {
+        f32::relative_eq(&self.x, &other.x, epsilon, max_relative)
+            && f32::relative_eq(&self.y, &other.y, epsilon, max_relative)
+            && f32::relative_eq(&self.z, &other.z, epsilon, max_relative)
+            && f32::relative_eq(&self.w, &other.w, epsilon, max_relative)
     }
 }
 impl fmt::Display for Tuple {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{:}, {:}, {:}, {:}", self.x, self.y, self.z, self.w)
-    }   
+    }
 }
 
 pub fn point(x: f32, y: f32, z: f32) -> Tuple {
@@ -169,7 +173,7 @@ pub fn vector(x: f32, y: f32, z: f32) -> Tuple {
 
 #[cfg(test)]
 mod tests {
-    use approx::{relative_eq, assert_relative_eq};
+    use approx::{assert_relative_eq, relative_eq};
 
     use crate::tuple::{point, vector, Tuple};
     #[test]
@@ -316,7 +320,7 @@ mod tests {
             z: 1.5,
             w: -2.0,
         };
-        assert_relative_eq!(b,c);
+        assert_relative_eq!(b, c);
     }
     #[test]
     fn div_by_scalar() {
@@ -379,7 +383,7 @@ mod tests {
         let a: Tuple = vector(1.0, 2.0, 3.0);
         let b: Tuple = a.normalize();
         let c: Tuple = vector(0.26726, 0.53452, 0.80178);
-        assert!(relative_eq!(b,c, epsilon = 0.00001))
+        assert!(relative_eq!(b, c, epsilon = 0.00001))
     }
     #[test]
     fn normalize3() {
@@ -404,7 +408,7 @@ mod tests {
     fn cross() {
         let a: Tuple = vector(1.0, 2.0, 3.0);
         let b: Tuple = vector(2.0, 3.0, 4.0);
-        let c = b.clone();
+        let c = b;
         assert_eq! {a.cross(b), vector(-1.0,2.0,-1.0)};
         assert_eq! {c.cross(a), vector(1.0,-2.0,1.0)};
     }
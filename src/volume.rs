@@ -0,0 +1,65 @@
+use crate::colour::Colour;
+
+/// Transmittance below which a medium is treated as opaque when marching through it.
+const OPAQUE: f32 = 0.001;
+
+/// A participating medium of even density, such as fog or smoke, that absorbs light travelling through it
+/// and scatters light from the lights back towards the eye.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Medium {
+    /// The colour of the medium when it is thick and fully lit.
+    pub colour: Colour,
+    /// How much light is absorbed or scattered per unit of distance.
+    pub density: f32,
+    /// The fraction of each light that reaches the medium even where it is shadowed, like `Material::ambient`.
+    pub ambient: f32,
+    /// Number of points marched along each ray segment to find the light scattered by the medium.
+    pub steps: usize,
+}
+impl Medium {
+    /// Create a medium from its colour and density
+    pub fn new(colour: Colour, density: f32) -> Medium {
+        Medium {
+            colour,
+            density,
+            ambient: 0.1,
+            steps: 16,
+        }
+    }
+    /// The fraction of light that passes through distance of the medium
+    pub fn transmittance(&self, distance: f32) -> f32 {
+        if self.density <= 0.0 {
+            1.0
+        } else {
+            (-self.density * distance).exp()
+        }
+    }
+    /// The distance after which almost no light gets through the medium
+    pub fn max_distance(&self) -> f32 {
+        if self.density <= 0.0 {
+            f32::INFINITY
+        } else {
+            -OPAQUE.ln() / self.density
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+
+    use super::Medium;
+    use crate::colour::WHITE;
+
+    #[test]
+    fn medium_transmittance() {
+        let fog = Medium::new(WHITE, 0.5);
+        assert_eq!(fog.transmittance(0.0), 1.0);
+        assert_relative_eq!(fog.transmittance(2.0), f32::exp(-1.0));
+        assert_relative_eq!(fog.transmittance(fog.max_distance()), 0.001);
+        assert_eq!(fog.transmittance(f32::INFINITY), 0.0);
+        let clear = Medium::new(WHITE, 0.0);
+        assert_eq!(clear.transmittance(100.0), 1.0);
+        assert_eq!(clear.max_distance(), f32::INFINITY);
+    }
+}
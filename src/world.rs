@@ -1,12 +1,75 @@
-use crate::{canvas::Canvas, colour::{self, Colour, BLACK}, matrix::{identity, Matrix4x4}, ray::{self, lighting, schlick, Computations, Intersections, Light, Ray}, shapes::Object, transformation::{scale, translation}, tuple::{point, Tuple}};
+use std::f32::consts::PI;
+
+use crate::{
+    canvas::Canvas,
+    colour::{self, Colour, BLACK},
+    dispersion::{combine_channels, Channel},
+    environment::{Background, EnvironmentLight},
+    integrator::{trace_path, AmbientOcclusion, Integrator},
+    matrix::{identity, Matrix4x4},
+    photon::PhotonMap,
+    ray::{
+        self, ambient_lighting, direct_lighting, schlick, Computations, Intersection,
+        Intersections, Light, Ray,
+    },
+    sampling::{cosine_hemisphere, random, uniform_sphere},
+    shapes::Object,
+    transformation::{scale, translation},
+    tuple::{point, Tuple},
+    volume::Medium,
+    DEFAULT_EPSILON,
+};
+/// How far away the environment is treated as being when tracing shadows towards it.
+const ENVIRONMENT_DISTANCE: f32 = 10000.0;
+/// How far past the back of a volume object rays continue from, so they do not hit it again.
+pub(crate) const VOLUME_EPSILON: f32 = 0.0001;
+/// How a ray has already been split into several rays further up the recursion, passed down along with the
+/// depth so that each kind of split happens once along a path rather than again at every bounce.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct Split {
+    /// Set once a glossy surface has averaged several rays, so glossy surfaces further along trace one each.
+    pub glossy: bool,
+    /// Set once a dispersive surface has traced one ray per colour channel, the only channel this ray carries.
+    pub channel: Option<Channel>,
+}
 pub struct World {
     pub objects: Vec<Object>,
     pub lights: Vec<ray::Light>,
+    /// Number of rays cast from each hit to gather light from emissive objects, 0 disables indirect lighting.
+    pub indirect_samples: usize,
+    /// How camera rays are turned into colours when rendering.
+    pub integrator: Integrator,
+    /// Scale the ambient term by how open the surface is, None leaves ambient light everywhere.
+    pub ambient_occlusion: Option<AmbientOcclusion>,
+    /// Caustics traced from the lights with `PhotonMap::trace`, added to diffuse surfaces in `shade_hit`.
+    /// The direct lighting in `shade_hit` then stops at transparent objects so their light is not counted twice.
+    pub photon_map: Option<PhotonMap>,
+    /// Seen by rays that miss every object, including reflected and refracted rays.
+    pub background: Background,
+    /// Light the world from an environment image as well as from the lights.
+    pub environment_light: Option<EnvironmentLight>,
+    /// Fog filling the whole world, applied along every ray segment. Light reaching surfaces is not dimmed by it.
+    pub fog: Option<Medium>,
 }
 
+impl Default for World {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 impl World {
     pub fn new() -> World {
-        World{ objects: Vec::new(), lights: Vec::new() }
+        World {
+            objects: Vec::new(),
+            lights: Vec::new(),
+            indirect_samples: 0,
+            integrator: Integrator::Whitted,
+            ambient_occlusion: None,
+            photon_map: None,
+            background: Background::default(),
+            environment_light: None,
+            fog: None,
+        }
     }
     // TODO use the default function
     pub fn default_world() -> World {
@@ -17,25 +80,65 @@ impl World {
         s1.material.specular = 0.2;
         let mut s2 = Object::new_sphere();
         s2.transform = scale(0.5, 0.5, 0.5);
-        World{ objects: vec![s1, s2], lights: vec![light],}
+        World {
+            objects: vec![s1, s2],
+            lights: vec![light],
+            ..World::new()
+        }
     }
     /// Find all the intersections of a ray and the objects in the world
     pub fn intersect(&self, ray: &Ray) -> Intersections {
         let mut xs = vec![];
-        for _o in self.objects.iter() {
-            let mut x = ray.intersect(&_o);
+        for (index, _o) in self.objects.iter().enumerate() {
+            let mut x = ray.intersect(_o);
+            for i in x.iter_mut() {
+                i.index = Some(index);
+            }
             xs.append(&mut x);
         }
         Intersections::new(xs)
     }
-    /// Calculate the shaded colour at a hit 
-    pub fn shade_hit(&self, comps: Computations, depth: usize) -> Colour {
-        let shadowed = self.is_shadowed(comps.over_point);
-        // TODO check there are any lights, iter over all
-        let surface_colour = lighting(comps.object, self.lights[0], comps.point, comps.eyev, comps.normalv, shadowed);
-        let reflected_colour = self.reflected_colour(&comps, depth);
-        let refracted_colour = self.refracted_colour(&comps, depth);
-        let material = comps.object.material;
+    /// Calculate the shaded colour at a hit
+    pub fn shade_hit(&self, comps: Computations, depth: usize, split: Split) -> Colour {
+        let subsurface = comps.object.material.subsurface.is_some();
+        let adjusted;
+        let object = if self.ambient_occlusion.is_none() && !subsurface {
+            &comps.object
+        } else {
+            let mut object = comps.object.clone();
+            if let Some(ao) = &self.ambient_occlusion {
+                object.material.ambient *= ao.visibility(self, comps.over_point, comps.normalv);
+            }
+            if subsurface {
+                // The diffuse light comes from under the surface instead
+                object.material.diffuse = 0.0;
+            }
+            adjusted = object;
+            &adjusted
+        };
+        // The ambient term stands in for light bounced around the whole scene, so it is taken from the first light
+        // once rather than added again for every light
+        let mut lit_colour = match self.lights.first() {
+            Some(light) => ambient_lighting(object, *light, comps.point),
+            None => BLACK,
+        };
+        for light in &self.lights {
+            let transmittance = self.direct_transmittance(comps.over_point, light.position);
+            if transmittance != BLACK {
+                lit_colour = lit_colour
+                    + direct_lighting(object, *light, comps.point, comps.eyev, comps.normalv)
+                        * transmittance;
+            }
+        }
+        let surface_colour = lit_colour
+            + comps.object.material.emission()
+            + self.indirect_emission(&comps)
+            + self.caustics(&comps)
+            + self.image_based_lighting(&comps)
+            + self.subsurface_colour(&comps);
+        let reflected_colour = self.reflected_colour(&comps, depth, split);
+        let refracted_colour = self.refracted_colour(&comps, depth, split);
+        let material = &comps.object.material;
         if material.reflective > 0.0 && material.transparency > 0.0 {
             let reflectance = schlick(comps);
             surface_colour + reflected_colour * reflectance + refracted_colour * (1.0 - reflectance)
@@ -45,77 +148,554 @@ impl World {
     }
     /// Intersect a ray with the world and find the shade if it hits
     pub fn colour_at(&self, ray: Ray, depth: usize) -> Colour {
-        let inters = self.intersect(&ray);
-        if let Some(hit) = inters.hit() {
-            let comps = ray.prepare_computations(&hit, inters);
-            self.shade_hit(comps, depth)
+        self.colour_and_distance(ray, depth, Split::default()).0
+    }
+    /// Intersect a ray with the world and find the shade and distance of the hit, misses are infinitely far away
+    fn colour_and_distance(&self, ray: Ray, depth: usize, split: Split) -> (Colour, f32) {
+        self.through_fog(ray, self.trace(ray, depth, split))
+    }
+    /// Apply the fog to the shade and distance found along a ray
+    fn through_fog(&self, ray: Ray, (colour, distance): (Colour, f32)) -> (Colour, f32) {
+        match &self.fog {
+            Some(fog) => (
+                self.through_medium(fog, ray, 0.0, distance, colour),
+                distance,
+            ),
+            None => (colour, distance),
+        }
+    }
+    /// Find the shade and distance along a ray, ignoring the fog
+    fn trace(&self, ray: Ray, depth: usize, split: Split) -> (Colour, f32) {
+        self.shade_intersections(ray, self.intersect(&ray), depth, split)
+    }
+    /// Find the shade and distance of the hit among the intersections of a ray, ignoring the fog
+    fn shade_intersections(
+        &self,
+        ray: Ray,
+        inters: Intersections,
+        depth: usize,
+        split: Split,
+    ) -> (Colour, f32) {
+        match inters.hit() {
+            Some(hit) => {
+                if let Some(medium) = hit.object.material.volume {
+                    return self.volume_colour(ray, &hit, &inters, &medium, depth, split);
+                }
+                let comps = ray.prepare_computations(&hit, inters);
+                let distance = comps.t;
+                (self.shade_hit(comps, depth, split), distance)
+            }
+            None => (self.background.colour_at(ray.direction), f32::INFINITY),
+        }
+    }
+    /// The light seen through a volume object at a hit, from whatever lies behind it and scattered by its medium
+    fn volume_colour(
+        &self,
+        ray: Ray,
+        hit: &Intersection,
+        inters: &Intersections,
+        medium: &Medium,
+        depth: usize,
+        split: Split,
+    ) -> (Colour, f32) {
+        let (start, end) = volume_span(&ray, hit, inters);
+        let exit_point = ray.position(end) + ray.direction.normalize() * VOLUME_EPSILON;
+        // The segment behind the volume passes through the fog on its own
+        let behind = if depth == 0 {
+            BLACK
         } else {
-            colour::BLACK
+            self.colour_and_distance(Ray::new(exit_point, ray.direction), depth - 1, split)
+                .0
+        };
+        let colour = self.through_medium(medium, ray, start, end, behind);
+        (colour, end + VOLUME_EPSILON)
+    }
+    /// Attenuate the light coming from t1 along the ray through a medium filling the ray from t0, and add the light
+    /// the medium scatters towards the ray's origin from the lights. The Whitted integrator samples the middle of
+    /// each step so renders are repeatable, the path tracer jitters the samples within each step.
+    pub(crate) fn through_medium(
+        &self,
+        medium: &Medium,
+        ray: Ray,
+        t0: f32,
+        t1: f32,
+        behind: Colour,
+    ) -> Colour {
+        if medium.density <= 0.0 {
+            return behind;
+        }
+        let attenuated = behind * medium.transmittance(t1 - t0);
+        let length = (t1 - t0).min(medium.max_distance());
+        if length <= 0.0 || !length.is_finite() {
+            return attenuated;
+        }
+        let steps = medium.steps.max(1);
+        let step = length / steps as f32;
+        let mut scattered = BLACK;
+        for i in 0..steps {
+            let offset = i as f32 * step;
+            // The share of the light that is scattered within this step and reaches the origin
+            let weight = medium.transmittance(offset) - medium.transmittance(offset + step);
+            let jitter = match self.integrator {
+                Integrator::Whitted => 0.5,
+                _ => random(),
+            };
+            let point = ray.position(t0 + offset + jitter * step);
+            let mut incoming = BLACK;
+            for light in &self.lights {
+                incoming = incoming
+                    + light.intensity
+                        * (self.shadow_transmittance(point, light.position)
+                            + colour::WHITE * medium.ambient);
+            }
+            scattered = scattered + incoming * weight;
         }
+        attenuated + medium.colour * scattered
     }
     /// Render the world from cam perspective
     pub fn render(self, cam: Camera) -> Canvas {
         let mut image = Canvas::new(cam.hsize, cam.vsize, BLACK);
-        for _x in 0..cam.hsize {                    
+        for _x in 0..cam.hsize {
             for _y in 0..cam.vsize {
-                let r = cam.ray_for_pixel(_x, _y);
-                let colour = self.colour_at(r, 5);
+                let colour = self.pixel_colour(&cam, _x, _y);
                 image.write_pixel(_x, _y, colour);
             }
         }
         image
     }
-    /// Check if the point is shadowed by any object in the world
+    /// Render the world from cam perspective along with the depth, normal, albedo and object ID passes.
+    /// The passes come from the hit of the ray through the centre of each pixel, which is also shaded for the image
+    /// unless the integrator jitters its camera rays.
+    pub fn render_with_aovs(self, cam: Camera) -> (Canvas, Aovs) {
+        let mut image = Canvas::new(cam.hsize, cam.vsize, BLACK);
+        let mut aovs = Aovs::new(cam.hsize, cam.vsize);
+        for _x in 0..cam.hsize {
+            for _y in 0..cam.vsize {
+                let r = cam.ray_for_pixel(_x, _y);
+                let inters = self.intersect(&r);
+                if let Some(hit) = inters.hit() {
+                    let comps = r.prepare_computations(&hit, inters.clone());
+                    let id = comps.index.map_or(-1.0, |i| i as f32);
+                    let n = comps.normalv;
+                    aovs.depth
+                        .write_pixel(_x, _y, Colour::new(comps.t, comps.t, comps.t));
+                    aovs.normal.write_pixel(_x, _y, Colour::new(n.x, n.y, n.z));
+                    aovs.albedo
+                        .write_pixel(_x, _y, comps.object.pattern_at(comps.point));
+                    aovs.object_id.write_pixel(_x, _y, Colour::new(id, id, id));
+                }
+                let colour = match self.integrator {
+                    Integrator::PathTraced { .. } => self.pixel_colour(&cam, _x, _y),
+                    _ => self.camera_ray_colour(r, inters),
+                };
+                image.write_pixel(_x, _y, colour);
+            }
+        }
+        (image, aovs)
+    }
+    /// Find the colour seen through a single pixel of the camera
+    fn pixel_colour(&self, cam: &Camera, px: usize, py: usize) -> Colour {
+        match self.integrator {
+            Integrator::PathTraced { samples, max_depth } => {
+                let samples = samples.max(1);
+                let mut total = BLACK;
+                for _ in 0..samples {
+                    let r = cam.ray_for_subpixel(px, py, random(), random());
+                    total = total + trace_path(self, r, max_depth);
+                }
+                total * (1.0 / samples as f32)
+            }
+            _ => {
+                let r = cam.ray_for_pixel(px, py);
+                self.camera_ray_colour(r, self.intersect(&r))
+            }
+        }
+    }
+    /// Find the colour of a camera ray from its intersections, for the integrators that cast one ray per pixel
+    fn camera_ray_colour(&self, r: Ray, inters: Intersections) -> Colour {
+        match self.integrator {
+            Integrator::AmbientOcclusion(ao) => match inters.hit() {
+                Some(hit) => {
+                    let comps = r.prepare_computations(&hit, inters);
+                    let visibility = ao.visibility(self, comps.over_point, comps.normalv);
+                    Colour::new(visibility, visibility, visibility)
+                }
+                None => colour::WHITE,
+            },
+            _ => {
+                let colour = self.shade_intersections(r, inters, 5, Split::default());
+                self.through_fog(r, colour).0
+            }
+        }
+    }
+    /// Check if the point is shadowed from the first light by the objects in the world, never without lights
     pub fn is_shadowed(&self, point: Tuple) -> bool {
-        // TODO do this for all lights
-        let v = self.lights[0].position - point;
+        match self.lights.first() {
+            Some(light) => self.shadow_transmittance(point, light.position) == BLACK,
+            None => false,
+        }
+    }
+    /// How much of each channel of light travelling from position to point gets past the objects between them.
+    /// Every occluder filters the light by its transparency and colour once, along with any absorption inside it,
+    /// and objects that do not cast shadows are ignored.
+    pub fn shadow_transmittance(&self, point: Tuple, position: Tuple) -> Colour {
+        self.transmittance_between(point, position, false)
+    }
+    /// How much of each channel of a light at position lights a surface at point directly. With a photon map the
+    /// light that transparent objects let through arrives as caustics instead, so here they block it.
+    fn direct_transmittance(&self, point: Tuple, position: Tuple) -> Colour {
+        self.transmittance_between(point, position, self.photon_map.is_some())
+    }
+    /// Shadow transmittance, with transparent surfaces treated as opaque when block_transparent is set
+    fn transmittance_between(
+        &self,
+        point: Tuple,
+        position: Tuple,
+        block_transparent: bool,
+    ) -> Colour {
+        let v = position - point;
         let distance = v.magnitude();
-        let direction = v.normalize();
-        let r = Ray::new(point, direction);
+        let r = Ray::new(point, v.normalize());
+        let mut transmittance = colour::WHITE;
+        // Where the shadow ray entered each object it has crossed
+        let mut entries: Vec<(&Intersection, f32)> = Vec::new();
         let inters = self.intersect(&r);
-        if let Some(hit) = inters.hit() {
-            if hit.t < distance { // TODO can I add tis to the if let pattern?
-                true
-            } else {
-                false
+        for inter in inters.inters.iter().filter(|i| i.t > 0.0 && i.t < distance) {
+            let object = &inter.object;
+            let material = &object.material;
+            if !material.casts_shadow {
+                continue;
+            }
+            if let Some(medium) = &material.volume {
+                if object.normal_at(r.position(inter.t)).dot(r.direction) < 0.0 {
+                    entries.push((inter, inter.t));
+                } else {
+                    // The shadow ray may have started inside the medium
+                    let entry = match entries.iter().position(|(o, _)| o.same_object(inter)) {
+                        Some(i) => entries.remove(i).1,
+                        None => 0.0,
+                    };
+                    transmittance = transmittance * medium.transmittance(inter.t - entry);
+                }
+                continue;
+            }
+            if block_transparent {
+                return BLACK;
+            }
+            match entries.iter().find(|(o, _)| o.same_object(inter)) {
+                Some((_, entry)) => {
+                    transmittance = transmittance * material.transmittance(inter.t - entry)
+                }
+                None => {
+                    let filter = object.pattern_at(r.position(inter.t)) * material.transparency;
+                    transmittance = transmittance * filter;
+                    entries.push((inter, inter.t));
+                }
+            }
+            if transmittance == BLACK {
+                break;
             }
-        } else {
-            false
         }
+        transmittance
     }
-    pub fn reflected_colour(&self, comps: &Computations, depth: usize) -> Colour {
-        if depth == 0 {
-            BLACK
-        } else if comps.object.material.reflective == 0.0 {
+    /// The diffuse light leaving a hit after scattering inside the object, traced as random walks that start
+    /// just under the surface and end where they leave the object
+    pub fn subsurface_colour(&self, comps: &Computations) -> Colour {
+        let object = &comps.object;
+        let subsurface = match &object.material.subsurface {
+            Some(subsurface) => subsurface,
+            None => return BLACK,
+        };
+        let mut total = BLACK;
+        for _ in 0..subsurface.samples {
+            let mut point = comps.under_point;
+            let mut direction = cosine_hemisphere(-comps.normalv);
+            let mut throughput = colour::WHITE;
+            for _ in 0..subsurface.max_steps {
+                let step = -(1.0 - random()).ln() * subsurface.mean_free_path;
+                let r = Ray::new(point, direction);
+                let exit = r
+                    .intersect(object)
+                    .iter()
+                    .map(|i| i.t)
+                    .filter(|t| *t > 0.0)
+                    .fold(f32::INFINITY, f32::min);
+                if exit < step {
+                    let exit_point = r.position(exit);
+                    let normal = object.normal_at(exit_point);
+                    total = total
+                        + throughput
+                            * self.irradiance(exit_point + normal * DEFAULT_EPSILON, normal);
+                    break;
+                }
+                point = r.position(step);
+                direction = uniform_sphere();
+                throughput = throughput * subsurface.colour;
+            }
+        }
+        total
+            * object.pattern_at(comps.point)
+            * (object.material.diffuse / subsurface.samples as f32)
+    }
+    /// The light from every light arriving at a point on a surface facing along normal
+    fn irradiance(&self, point: Tuple, normal: Tuple) -> Colour {
+        let mut total = BLACK;
+        for light in &self.lights {
+            let cos = (light.position - point).normalize().dot(normal);
+            if cos > 0.0 {
+                total = total
+                    + light.intensity * self.shadow_transmittance(point, light.position) * cos;
+            }
+        }
+        total
+    }
+    /// The light focused onto a hit by specular objects, estimated from the photon map
+    pub fn caustics(&self, comps: &Computations) -> Colour {
+        match &self.photon_map {
+            Some(map) => {
+                let material = &comps.object.material;
+                map.irradiance(comps.point, comps.normalv)
+                    * comps.object.pattern_at(comps.point)
+                    * material.diffuse
+            }
+            None => BLACK,
+        }
+    }
+    /// The light reaching a hit from the environment light, gathered along importance sampled directions
+    pub fn image_based_lighting(&self, comps: &Computations) -> Colour {
+        let environment = match &self.environment_light {
+            Some(environment) => environment,
+            None => return BLACK,
+        };
+        let mut total = BLACK;
+        for _ in 0..environment.samples {
+            let (direction, radiance, pdf) = environment.sample();
+            if pdf <= 0.0 || direction.dot(comps.normalv) <= 0.0 {
+                continue;
+            }
+            let far = comps.over_point + direction * ENVIRONMENT_DISTANCE;
+            let transmittance = self.shadow_transmittance(comps.over_point, far);
+            if transmittance == BLACK {
+                continue;
+            }
+            // Each direction acts as a distant light, scaled so the diffuse term integrates the radiance over the hemisphere
+            let light = Light::new(far, radiance * (1.0 / (PI * pdf)));
+            let reflected = match &comps.object.material.pbr {
+                // The GGX lobe is normalized, so the whole BRDF is estimated correctly
+                Some(_) => {
+                    direct_lighting(&comps.object, light, comps.point, comps.eyev, comps.normalv)
+                }
+                // The Phong highlight is not normalized, so only the Lambert term is estimated
+                None => {
+                    let cos = direction.dot(comps.normalv);
+                    comps.object.pattern_at(comps.point)
+                        * light.intensity
+                        * (comps.object.material.diffuse * cos)
+                }
+            };
+            total = total + reflected * transmittance;
+        }
+        total * (1.0 / environment.samples as f32)
+    }
+    /// Gather the light from emissive objects that reaches a hit, by casting cosine weighted rays into the hemisphere around the normal
+    pub fn indirect_emission(&self, comps: &Computations) -> Colour {
+        if self.indirect_samples == 0 {
+            return BLACK;
+        }
+        let mut incoming = BLACK;
+        for _ in 0..self.indirect_samples {
+            let r = Ray::new(comps.over_point, cosine_hemisphere(comps.normalv));
+            if let Some(hit) = self.intersect(&r).hit() {
+                incoming = incoming + hit.object.material.emission();
+            }
+        }
+        // With cosine weighted sampling the Lambert cosine and pdf cancel, leaving the mean of the incoming light
+        let material = &comps.object.material;
+        comps.object.pattern_at(comps.point)
+            * incoming
+            * (material.diffuse / self.indirect_samples as f32)
+    }
+    pub fn reflected_colour(&self, comps: &Computations, depth: usize, split: Split) -> Colour {
+        if depth == 0 || comps.object.material.reflective == 0.0 {
             BLACK
         } else {
-            let reflected_ray = Ray::new(comps.over_point, comps.reflectv);
-            let colour = self.colour_at(reflected_ray, depth-1);
+            let colour = match &comps.object.material.glossy {
+                None => self.reflect(comps, comps.reflectv, depth, split),
+                Some(glossy) => glossy_average(glossy.samples, split, |split| {
+                    let direction = glossy.scatter(comps.reflectv, comps.normalv);
+                    self.reflect(comps, direction, depth, split)
+                }),
+            };
             colour * comps.object.material.reflective
         }
     }
-    pub fn refracted_colour(&self, comps: &Computations, depth: usize) -> Colour {   
-        let n_ratio = comps.n1/comps.n2;
+    /// The light refracted through a hit. The first dispersive surface along a path splits it into a ray per
+    /// channel, and each of those keeps to the wavelength of its channel through any later dispersive surfaces.
+    pub fn refracted_colour(&self, comps: &Computations, depth: usize, split: Split) -> Colour {
+        let material = &comps.object.material;
+        if depth == 0 || material.transparency == 0.0 {
+            return BLACK;
+        }
+        let colour = match &material.dispersion {
+            None => self.refract(comps, comps.n1, comps.n2, depth, split),
+            Some(dispersion) => {
+                // The index of this object is replaced by its index at the wavelength of the channel
+                let refract_channel = |channel: Channel| {
+                    let n = dispersion.index_at(channel.wavelength());
+                    let split = Split {
+                        channel: Some(channel),
+                        ..split
+                    };
+                    if comps.inside {
+                        self.refract(comps, n, comps.n2, depth, split)
+                    } else {
+                        self.refract(comps, comps.n1, n, depth, split)
+                    }
+                };
+                match split.channel {
+                    Some(channel) => refract_channel(channel),
+                    None => combine_channels(refract_channel),
+                }
+            }
+        };
+        colour * material.transparency
+    }
+    /// Trace the ray refracted from n1 into n2 at a hit, black for total internal reflection
+    fn refract(
+        &self,
+        comps: &Computations,
+        n1: f32,
+        n2: f32,
+        depth: usize,
+        split: Split,
+    ) -> Colour {
+        let n_ratio = n1 / n2;
         let cos_i = comps.eyev.dot(comps.normalv);
-        let sin2_t = f32::powi(n_ratio, 2)*(1.0 - f32::powi(cos_i, 2));
-        if depth == 0 {
-            BLACK
-        } else if comps.object.material.transparency == 0.0 {
-            BLACK
-        } else if sin2_t > 1.0 {
-            BLACK
-        } else {            
-            let cos_t = f32::sqrt(1.0-sin2_t);
-            let direction = comps.normalv * (n_ratio * cos_i - cos_t) - comps.eyev * n_ratio;
-            let refracted_ray = Ray::new(comps.under_point, direction);
-            let colour = self.colour_at(refracted_ray, depth-1);
-            colour * comps.object.material.transparency
+        let sin2_t = f32::powi(n_ratio, 2) * (1.0 - f32::powi(cos_i, 2));
+        if sin2_t > 1.0 {
+            return BLACK;
+        }
+        let cos_t = f32::sqrt(1.0 - sin2_t);
+        let direction = comps.normalv * (n_ratio * cos_i - cos_t) - comps.eyev * n_ratio;
+        match &comps.object.material.glossy {
+            None => self.transmit(comps, direction, depth, split),
+            Some(glossy) => glossy_average(glossy.samples, split, |split| {
+                self.transmit(
+                    comps,
+                    glossy.scatter(direction, comps.normalv),
+                    depth,
+                    split,
+                )
+            }),
+        }
+    }
+    /// The colour seen along a reflected direction. Light reflected back inside an object, as in total internal
+    /// reflection, travels through it and is absorbed on the way.
+    fn reflect(
+        &self,
+        comps: &Computations,
+        direction: Tuple,
+        depth: usize,
+        split: Split,
+    ) -> Colour {
+        let r = Ray::new(comps.over_point, direction);
+        let colour = self.colour_and_distance(r, depth - 1, split).0;
+        if comps.inside {
+            self.absorb_inside(&comps.object, r, colour)
+        } else {
+            colour
+        }
+    }
+    /// The colour seen along a refracted direction, absorbed by the object when the ray travels into it
+    fn transmit(
+        &self,
+        comps: &Computations,
+        direction: Tuple,
+        depth: usize,
+        split: Split,
+    ) -> Colour {
+        let r = Ray::new(comps.under_point, direction);
+        let colour = self.colour_and_distance(r, depth - 1, split).0;
+        if comps.inside {
+            colour
+        } else {
+            self.absorb_inside(&comps.object, r, colour)
+        }
+    }
+    /// Absorb the light seen along a ray that starts inside object over the chord to where the ray leaves it.
+    /// Objects inside it do not cut the chord short, and open surfaces that the ray never leaves do not absorb.
+    fn absorb_inside(&self, object: &Object, r: Ray, colour: Colour) -> Colour {
+        let exit = r
+            .intersect(object)
+            .iter()
+            .map(|i| i.t)
+            .filter(|t| *t > 0.0)
+            .fold(f32::INFINITY, f32::min);
+        if exit == f32::INFINITY {
+            colour
+        } else {
+            colour * object.material.transmittance(exit)
+        }
+    }
+}
+/// Where a ray is inside the medium of the volume object it hits, from the start of the ray when it began inside
+pub(crate) fn volume_span(ray: &Ray, hit: &Intersection, inters: &Intersections) -> (f32, f32) {
+    let t = hit.t;
+    let entering = hit.object.normal_at(ray.position(t)).dot(ray.direction) < 0.0;
+    if entering {
+        let exit = inters
+            .inters
+            .iter()
+            .find(|i| i.t > t && i.same_object(hit))
+            .map_or(t, |i| i.t);
+        (t, exit)
+    } else {
+        (0.0, t)
+    }
+}
+/// Average samples of a glossy lobe. Only the first glossy bounce along a path is split into samples rays,
+/// later ones trace a single ray so the cost does not grow as samples to the power of the depth.
+fn glossy_average(samples: usize, split: Split, mut trace: impl FnMut(Split) -> Colour) -> Colour {
+    if split.glossy {
+        return trace(split);
+    }
+    let split = Split {
+        glossy: true,
+        ..split
+    };
+    let mut total = BLACK;
+    for _ in 0..samples {
+        total = total + trace(split);
+    }
+    total * (1.0 / samples as f32)
+}
+/// Arbitrary output variables, extra per-pixel buffers written by `World::render_with_aovs`.
+/// Values are stored unclamped in every channel so they can be used for compositing.
+pub struct Aovs {
+    /// Distance along the camera ray to the hit. Misses are `f32::INFINITY`.
+    pub depth: Canvas,
+    /// World space normal at the hit, facing the camera, with x, y and z in red, green and blue.
+    pub normal: Canvas,
+    /// Unshaded pattern colour at the hit.
+    pub albedo: Canvas,
+    /// Index of the hit object in `World::objects`. Misses are -1.0.
+    pub object_id: Canvas,
+}
+impl Aovs {
+    /// Create empty buffers of size width x height
+    pub fn new(width: usize, height: usize) -> Aovs {
+        let inf = Colour::new(f32::INFINITY, f32::INFINITY, f32::INFINITY);
+        Aovs {
+            depth: Canvas::new(width, height, inf),
+            normal: Canvas::new(width, height, BLACK),
+            albedo: Canvas::new(width, height, BLACK),
+            object_id: Canvas::new(width, height, Colour::new(-1.0, -1.0, -1.0)),
         }
     }
 }
 /// Create a view transformation matrix
 pub fn view_transform(from: Tuple, to: Tuple, up: Tuple) -> Matrix4x4 {
-    let forward = (to-from).normalize();
+    let forward = (to - from).normalize();
     let upn = up.normalize();
     let left = forward.cross(upn);
     let true_up = left.cross(forward);
@@ -130,7 +710,7 @@ pub fn view_transform(from: Tuple, to: Tuple, up: Tuple) -> Matrix4x4 {
     orientation.write_value(2, 1, -forward.y);
     orientation.write_value(2, 2, -forward.z);
     orientation.write_value(3, 3, 1.0);
-    orientation*translation(-from.x, -from.y, -from.z)
+    orientation * translation(-from.x, -from.y, -from.z)
 }
 pub struct Camera {
     pub hsize: usize,
@@ -144,38 +724,67 @@ pub struct Camera {
 impl Camera {
     pub fn new(hsize: usize, vsize: usize, fow: f32) -> Camera {
         let transform = identity();
-        let half_view = (fow/2.0).tan();
-        let aspect = hsize as f32/vsize as f32;
+        let half_view = (fow / 2.0).tan();
+        let aspect = hsize as f32 / vsize as f32;
         let half_width;
         let half_height;
         if aspect >= 1.0 {
             half_width = half_view;
-            half_height = half_view/aspect;
-        } else {            
-            half_width = half_view*aspect;
+            half_height = half_view / aspect;
+        } else {
+            half_width = half_view * aspect;
             half_height = half_view;
         }
-        let pixel_size = (half_width*2.0)/hsize as f32;
-        Camera{hsize, vsize, fow, transform, pixel_size, half_width, half_height}
+        let pixel_size = (half_width * 2.0) / hsize as f32;
+        Camera {
+            hsize,
+            vsize,
+            fow,
+            transform,
+            pixel_size,
+            half_width,
+            half_height,
+        }
     }
     pub fn ray_for_pixel(&self, px: usize, py: usize) -> Ray {
-        let x_offset = (px as f32 + 0.5) * self.pixel_size;
-        let y_offset = (py as f32 + 0.5) * self.pixel_size;
+        self.ray_for_subpixel(px, py, 0.5, 0.5)
+    }
+    /// A ray through a point inside a pixel, dx and dy run from 0.0 to 1.0 across the pixel
+    pub fn ray_for_subpixel(&self, px: usize, py: usize, dx: f32, dy: f32) -> Ray {
+        let x_offset = (px as f32 + dx) * self.pixel_size;
+        let y_offset = (py as f32 + dy) * self.pixel_size;
         let world_x = self.half_width - x_offset;
         let world_y = self.half_height - y_offset;
         let pixel = self.transform.inverse() * point(world_x, world_y, -1.0);
         let origin = self.transform.inverse() * point(0.0, 0.0, 0.0);
-        let direction = (pixel-origin).normalize();
+        let direction = (pixel - origin).normalize();
         Ray::new(origin, direction)
     }
 }
 #[cfg(test)]
 mod tests {
-    use std::{f32::consts::PI, vec};
+    use super::{glossy_average, view_transform, Split, World};
+    use crate::{
+        canvas::Canvas,
+        colour::{self, Colour, BLACK, RED, WHITE},
+        dispersion::{Channel, Dispersion},
+        environment::{Background, EnvironmentLight},
+        integrator::{AmbientOcclusion, Integrator},
+        matrix::{identity, Matrix4x4},
+        photon::PhotonMap,
+        ray::{Intersection, Intersections, Light, Ray},
+        sampling::seed,
+        shapes::{Glossy, Object, Pattern, Subsurface},
+        sky::PhysicalSky,
+        transformation::{rot_x, rot_y, scale, translation},
+        tuple::{point, vector},
+        volume::Medium,
+        world::Camera,
+        DEFAULT_EPSILON,
+    };
     use approx::assert_relative_eq;
-    use super::{World, view_transform};
-    use crate::{colour::{self, Colour, BLACK, RED, WHITE}, matrix::{identity, Matrix4x4}, ray::{Intersection, Intersections, Light, Ray}, shapes::{Object, Pattern}, transformation::{rot_y, scale, translation}, tuple::{point, vector}, world::Camera, DEFAULT_EPSILON};
-    
+    use std::{f32::consts::PI, sync::Arc, vec};
+
     #[test]
     fn create_world() {
         let world = World::new();
@@ -211,22 +820,30 @@ mod tests {
     fn shading_intersection() {
         let world = World::default_world();
         let r = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
-        let s =  world.objects[0];
-        let x = Intersection::new(4.0, s);
-        let comps = r.prepare_computations(&x, Intersections::new(vec![x]));
-        let c = world.shade_hit(comps, 5);
-        assert_relative_eq!(c, Colour::new(0.38066, 0.47583, 0.2855), epsilon=DEFAULT_EPSILON);
+        let s = &world.objects[0];
+        let x = Intersection::new(4.0, s.clone());
+        let comps = r.prepare_computations(&x, Intersections::new(vec![x.clone()]));
+        let c = world.shade_hit(comps, 5, Split::default());
+        assert_relative_eq!(
+            c,
+            Colour::new(0.38066, 0.47583, 0.2855),
+            epsilon = DEFAULT_EPSILON
+        );
     }
     #[test]
     fn shading_intersection_outside() {
         let mut world = World::default_world();
         world.lights = vec![Light::new(point(0.0, 0.25, 0.0), colour::WHITE)];
         let r = Ray::new(point(0.0, 0.0, 0.0), vector(0.0, 0.0, 1.0));
-        let s =  world.objects[1];
-        let x = Intersection::new(0.5, s);
-        let comps = r.prepare_computations(&x, Intersections::new(vec![x]));
-        let c = world.shade_hit(comps, 5);
-        assert_relative_eq!(c, Colour::new(0.90498, 0.90498, 0.90498), epsilon=DEFAULT_EPSILON);
+        let s = &world.objects[1];
+        let x = Intersection::new(0.5, s.clone());
+        let comps = r.prepare_computations(&x, Intersections::new(vec![x.clone()]));
+        let c = world.shade_hit(comps, 5, Split::default());
+        assert_relative_eq!(
+            c,
+            Colour::new(0.90498, 0.90498, 0.90498),
+            epsilon = DEFAULT_EPSILON
+        );
     }
     #[test]
     fn colour_ray_misses() {
@@ -240,7 +857,11 @@ mod tests {
         let world = World::default_world();
         let r = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
         let c = world.colour_at(r, 5);
-        assert_relative_eq!(c, Colour::new(0.38066, 0.47583, 0.2855), epsilon=DEFAULT_EPSILON);
+        assert_relative_eq!(
+            c,
+            Colour::new(0.38066, 0.47583, 0.2855),
+            epsilon = DEFAULT_EPSILON
+        );
     }
     #[test]
     fn trans_matrix_default() {
@@ -285,58 +906,125 @@ mod tests {
         m.write_value(2, 1, 0.59761);
         m.write_value(2, 2, -0.71714);
         m.write_value(3, 3, 1.0);
-        assert_relative_eq!(t, m, epsilon=DEFAULT_EPSILON);
+        assert_relative_eq!(t, m, epsilon = DEFAULT_EPSILON);
     }
     #[test]
     fn create_camera() {
-        let cam = Camera::new(160, 120, PI/2.0);
+        let cam = Camera::new(160, 120, PI / 2.0);
         assert_eq!(cam.hsize, 160);
         assert_eq!(cam.vsize, 120);
-        assert_eq!(cam.fow, PI/2.0);
+        assert_eq!(cam.fow, PI / 2.0);
         assert_eq!(cam.transform, identity());
     }
     #[test]
     fn create_camera_pixel_size() {
-        let cam = Camera::new(200, 125, PI/2.0);
+        let cam = Camera::new(200, 125, PI / 2.0);
         assert_eq!(cam.pixel_size, 0.01);
     }
     #[test]
     fn create_camera_pixel_size_vert() {
-        let cam = Camera::new(125, 200, PI/2.0);
+        let cam = Camera::new(125, 200, PI / 2.0);
         assert_eq!(cam.pixel_size, 0.01);
     }
     #[test]
     fn camera_create_ray_center() {
-        let cam = Camera::new(201, 101, PI/2.0);
+        let cam = Camera::new(201, 101, PI / 2.0);
         let r = cam.ray_for_pixel(100, 50);
         assert_eq!(r.origin, point(0.0, 0.0, 0.0));
-        assert_relative_eq!(r.direction, vector(0.0, 0.0, -1.0), epsilon=DEFAULT_EPSILON);
+        assert_relative_eq!(
+            r.direction,
+            vector(0.0, 0.0, -1.0),
+            epsilon = DEFAULT_EPSILON
+        );
     }
     #[test]
     fn camera_create_ray_corner() {
-        let cam = Camera::new(201, 101, PI/2.0);
+        let cam = Camera::new(201, 101, PI / 2.0);
         let r = cam.ray_for_pixel(0, 0);
         assert_eq!(r.origin, point(0.0, 0.0, 0.0));
-        assert_relative_eq!(r.direction, vector(0.66519, 0.33259, -0.66851), epsilon=DEFAULT_EPSILON);
+        assert_relative_eq!(
+            r.direction,
+            vector(0.66519, 0.33259, -0.66851),
+            epsilon = DEFAULT_EPSILON
+        );
     }
     #[test]
     fn camera_create_ray_transformed() {
-        let mut cam = Camera::new(201, 101, PI/2.0);
-        cam.transform = rot_y(PI/4.0)*translation(0.0, -2.0, 5.0);
+        let mut cam = Camera::new(201, 101, PI / 2.0);
+        cam.transform = rot_y(PI / 4.0) * translation(0.0, -2.0, 5.0);
         let r = cam.ray_for_pixel(100, 50);
         assert_eq!(r.origin, point(0.0, 2.0, -5.0000005));
-        assert_relative_eq!(r.direction, vector(f32::sqrt(2.0)/2.0, 0.0, -f32::sqrt(2.0)/2.0), epsilon=DEFAULT_EPSILON);
+        assert_relative_eq!(
+            r.direction,
+            vector(f32::sqrt(2.0) / 2.0, 0.0, -f32::sqrt(2.0) / 2.0),
+            epsilon = DEFAULT_EPSILON
+        );
     }
     #[test]
     fn render_world() {
         let world = World::default_world();
-        let mut cam = Camera::new(11, 11, PI/2.0);        
+        let mut cam = Camera::new(11, 11, PI / 2.0);
         let from = point(0.0, 0.0, -5.0);
         let to = point(0.0, 0.0, 0.0);
         let up = vector(0.0, 1.0, 0.0);
         cam.transform = view_transform(from, to, up);
         let image = world.render(cam);
-        assert_relative_eq!(image.pixel_at(5, 5), Colour::new(0.38066, 0.47583, 0.2855), epsilon=DEFAULT_EPSILON);
+        assert_relative_eq!(
+            image.pixel_at(5, 5),
+            Colour::new(0.38066, 0.47583, 0.2855),
+            epsilon = DEFAULT_EPSILON
+        );
+    }
+    #[test]
+    fn render_world_with_aovs() {
+        let world = World::default_world();
+        let mut cam = Camera::new(11, 11, PI / 2.0);
+        cam.transform = view_transform(
+            point(0.0, 0.0, -5.0),
+            point(0.0, 0.0, 0.0),
+            vector(0.0, 1.0, 0.0),
+        );
+        let (image, aovs) = world.render_with_aovs(cam);
+        assert_relative_eq!(
+            image.pixel_at(5, 5),
+            Colour::new(0.38066, 0.47583, 0.2855),
+            epsilon = DEFAULT_EPSILON
+        );
+        assert_relative_eq!(
+            aovs.depth.pixel_at(5, 5),
+            Colour::new(4.0, 4.0, 4.0),
+            epsilon = DEFAULT_EPSILON
+        );
+        assert_relative_eq!(
+            aovs.normal.pixel_at(5, 5),
+            Colour::new(0.0, 0.0, -1.0),
+            epsilon = DEFAULT_EPSILON
+        );
+        assert_eq!(aovs.albedo.pixel_at(5, 5), Colour::new(0.8, 1.0, 0.6));
+        assert_eq!(aovs.object_id.pixel_at(5, 5), Colour::new(0.0, 0.0, 0.0));
+    }
+    #[test]
+    fn aovs_for_missed_pixel() {
+        let world = World::default_world();
+        let mut cam = Camera::new(11, 11, PI / 2.0);
+        cam.transform = view_transform(
+            point(0.0, 0.0, -5.0),
+            point(0.0, 0.0, 0.0),
+            vector(0.0, 1.0, 0.0),
+        );
+        let (image, aovs) = world.render_with_aovs(cam);
+        assert_eq!(image.pixel_at(0, 0), BLACK);
+        assert_eq!(aovs.depth.pixel_at(0, 0).red, f32::INFINITY);
+        assert_eq!(aovs.normal.pixel_at(0, 0), BLACK);
+        assert_eq!(aovs.object_id.pixel_at(0, 0), Colour::new(-1.0, -1.0, -1.0));
+    }
+    #[test]
+    fn intersections_carry_object_index() {
+        let world = World::default_world();
+        let r = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        let xs = world.intersect(&r);
+        let indices: Vec<Option<usize>> = xs.inters.iter().map(|i| i.index).collect();
+        assert_eq!(indices, vec![Some(0), Some(1), Some(1), Some(0)]);
     }
     #[test]
     fn shadow_default_world() {
@@ -368,15 +1056,19 @@ mod tests {
     }
     #[test]
     fn shade_hit_in_shadow() {
-        let light = Light::new(point(0.0,0.0, -10.0), colour::WHITE);
+        let light = Light::new(point(0.0, 0.0, -10.0), colour::WHITE);
         let s1 = Object::new_sphere();
         let mut s2 = Object::new_sphere();
-        s2.transform =translation(0.0, 0.0, 10.0);
-        let world = World{ objects: vec![s1, s2], lights: vec![light],};
+        s2.transform = translation(0.0, 0.0, 10.0);
+        let world = World {
+            objects: vec![s1, s2.clone()],
+            lights: vec![light],
+            ..World::new()
+        };
         let r = Ray::new(point(0.0, 0.0, 5.0), vector(0.0, 0.0, 1.0));
-        let i = Intersection::new(4.0, s2);
-        let comps = r.prepare_computations(&i, Intersections::new(vec![i]));
-        let c = world.shade_hit(comps, 5);
+        let i = Intersection::new(4.0, s2.clone());
+        let comps = r.prepare_computations(&i, Intersections::new(vec![i.clone()]));
+        let c = world.shade_hit(comps, 5, Split::default());
         assert_eq!(c, Colour::new(0.1, 0.1, 0.1));
     }
     #[test]
@@ -384,9 +1076,9 @@ mod tests {
         let mut world = World::default_world();
         let r = Ray::new(point(0.0, 0.0, 0.0), vector(0.0, 0.0, 1.0));
         world.objects[1].material.ambient = 1.0;
-        let i = Intersection::new(1.0, world.objects[1]);
-        let comps = r.prepare_computations(&i, Intersections::new(vec![i]));
-        assert_eq!(world.reflected_colour(&comps, 5), BLACK);
+        let i = Intersection::new(1.0, world.objects[1].clone());
+        let comps = r.prepare_computations(&i, Intersections::new(vec![i.clone()]));
+        assert_eq!(world.reflected_colour(&comps, 5, Split::default()), BLACK);
     }
     #[test]
     fn reflected_colour_for_reflective() {
@@ -394,11 +1086,18 @@ mod tests {
         let mut shape = Object::new_plane();
         shape.material.reflective = 0.5;
         shape.transform = translation(0.0, -1.0, 0.0);
-        world.objects.push(shape);        
-        let r = Ray::new(point(0.0, 0.0, -3.0), vector(0.0, -(f32::sqrt(2.0)/2.0), f32::sqrt(2.0)/2.0));
-        let i = Intersection::new(f32::sqrt(2.0), world.objects[2]);
-        let comps = r.prepare_computations(&i, Intersections::new(vec![i]));
-        assert_relative_eq!(world.reflected_colour(&comps, 5), Colour::new(0.190332, 0.23791, 0.14274), epsilon=DEFAULT_EPSILON);
+        world.objects.push(shape);
+        let r = Ray::new(
+            point(0.0, 0.0, -3.0),
+            vector(0.0, -(f32::sqrt(2.0) / 2.0), f32::sqrt(2.0) / 2.0),
+        );
+        let i = Intersection::new(f32::sqrt(2.0), world.objects[2].clone());
+        let comps = r.prepare_computations(&i, Intersections::new(vec![i.clone()]));
+        assert_relative_eq!(
+            world.reflected_colour(&comps, 5, Split::default()),
+            Colour::new(0.190332, 0.23791, 0.14274),
+            epsilon = DEFAULT_EPSILON
+        );
     }
     #[test]
     fn shade_hit_with_reflective() {
@@ -406,11 +1105,18 @@ mod tests {
         let mut shape = Object::new_plane();
         shape.material.reflective = 0.5;
         shape.transform = translation(0.0, -1.0, 0.0);
-        world.objects.push(shape);        
-        let r = Ray::new(point(0.0, 0.0, -3.0), vector(0.0, -(f32::sqrt(2.0)/2.0), f32::sqrt(2.0)/2.0));
-        let i = Intersection::new(f32::sqrt(2.0), world.objects[2]);
-        let comps = r.prepare_computations(&i, Intersections::new(vec![i]));
-        assert_relative_eq!(world.shade_hit(comps, 5), Colour::new(0.87675, 0.92434, 0.82918), epsilon=DEFAULT_EPSILON);
+        world.objects.push(shape);
+        let r = Ray::new(
+            point(0.0, 0.0, -3.0),
+            vector(0.0, -(f32::sqrt(2.0) / 2.0), f32::sqrt(2.0) / 2.0),
+        );
+        let i = Intersection::new(f32::sqrt(2.0), world.objects[2].clone());
+        let comps = r.prepare_computations(&i, Intersections::new(vec![i.clone()]));
+        assert_relative_eq!(
+            world.shade_hit(comps, 5, Split::default()),
+            Colour::new(0.87675, 0.92434, 0.82918),
+            epsilon = DEFAULT_EPSILON
+        );
     }
     #[test]
     fn colour_between_mirrors() {
@@ -419,13 +1125,17 @@ mod tests {
         let mut lower = Object::new_plane();
         lower.material.reflective = 1.0;
         lower.transform = translation(0.0, -1.0, 0.0);
-        world.objects.push(lower);        
+        world.objects.push(lower);
         let mut upper = Object::new_plane();
         upper.material.reflective = 1.0;
         upper.transform = translation(0.0, 1.0, 0.0);
-        world.objects.push(upper);        
+        world.objects.push(upper);
         let r = Ray::new(point(0.0, 0.0, 0.0), vector(0.0, 1.0, 0.0));
-        assert_relative_eq!(world.colour_at(r, 5), Colour::new(11.4, 11.4, 11.4), epsilon=DEFAULT_EPSILON);
+        assert_relative_eq!(
+            world.colour_at(r, 5),
+            Colour::new(11.4, 11.4, 11.4),
+            epsilon = DEFAULT_EPSILON
+        );
     }
     #[test]
     fn reflected_colour_at_recursion_depth() {
@@ -433,45 +1143,54 @@ mod tests {
         let mut shape = Object::new_plane();
         shape.material.reflective = 0.5;
         shape.transform = translation(0.0, -1.0, 0.0);
-        world.objects.push(shape);        
-        let r = Ray::new(point(0.0, 0.0, -3.0), vector(0.0, -(f32::sqrt(2.0)/2.0), f32::sqrt(2.0)/2.0));
-        let i = Intersection::new(f32::sqrt(2.0), world.objects[2]);
-        let comps = r.prepare_computations(&i, Intersections::new(vec![i]));
-        assert_eq!(world.reflected_colour(&comps, 0), BLACK);
+        world.objects.push(shape);
+        let r = Ray::new(
+            point(0.0, 0.0, -3.0),
+            vector(0.0, -(f32::sqrt(2.0) / 2.0), f32::sqrt(2.0) / 2.0),
+        );
+        let i = Intersection::new(f32::sqrt(2.0), world.objects[2].clone());
+        let comps = r.prepare_computations(&i, Intersections::new(vec![i.clone()]));
+        assert_eq!(world.reflected_colour(&comps, 0, Split::default()), BLACK);
     }
     #[test]
     fn refracted_colour_from_opaque_object() {
         let world = World::default_world();
-        let shape = world.objects[0];
+        let shape = world.objects[0].clone();
         let r = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
-        let xs = Intersections::new(vec![Intersection::new(4.0, shape),
-                                                                Intersection::new(6.0, shape)]);
+        let xs = Intersections::new(vec![
+            Intersection::new(4.0, shape.clone()),
+            Intersection::new(6.0, shape.clone()),
+        ]);
         let comps = r.prepare_computations(&xs.inters[0].clone(), xs);
-        assert_eq!(world.refracted_colour(&comps, 5), BLACK);
+        assert_eq!(world.refracted_colour(&comps, 5, Split::default()), BLACK);
     }
     #[test]
     fn refracted_colour_at_recursion_depth() {
         let world = World::default_world();
-        let mut shape = world.objects[0];
+        let mut shape = world.objects[0].clone();
         shape.material.transparency = 1.0;
         shape.material.refractive_index = 1.5;
         let r = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
-        let xs = Intersections::new(vec![Intersection::new(4.0, shape),
-                                                                Intersection::new(6.0, shape)]);
+        let xs = Intersections::new(vec![
+            Intersection::new(4.0, shape.clone()),
+            Intersection::new(6.0, shape.clone()),
+        ]);
         let comps = r.prepare_computations(&xs.inters[0].clone(), xs);
-        assert_eq!(world.refracted_colour(&comps, 0), BLACK);
+        assert_eq!(world.refracted_colour(&comps, 0, Split::default()), BLACK);
     }
     #[test]
     fn total_internal_reflection() {
         let world = World::default_world();
-        let mut shape = world.objects[0];
+        let mut shape = world.objects[0].clone();
         shape.material.transparency = 1.0;
         shape.material.refractive_index = 1.5;
-        let r = Ray::new(point(0.0, 0.0, f32::sqrt(2.0)/2.0), vector(0.0, 1.0, 0.0));
-        let xs = Intersections::new(vec![Intersection::new(-f32::sqrt(2.0)/2.0, shape),
-                                                                Intersection::new(f32::sqrt(2.0)/2.0, shape)]);
+        let r = Ray::new(point(0.0, 0.0, f32::sqrt(2.0) / 2.0), vector(0.0, 1.0, 0.0));
+        let xs = Intersections::new(vec![
+            Intersection::new(-f32::sqrt(2.0) / 2.0, shape.clone()),
+            Intersection::new(f32::sqrt(2.0) / 2.0, shape.clone()),
+        ]);
         let comps = r.prepare_computations(&xs.inters[1].clone(), xs);
-        assert_eq!(world.refracted_colour(&comps, 5), BLACK);
+        assert_eq!(world.refracted_colour(&comps, 5, Split::default()), BLACK);
     }
     #[test]
     fn finding_refracted_colour() {
@@ -481,12 +1200,18 @@ mod tests {
         world.objects[1].material.transparency = 1.0;
         world.objects[1].material.refractive_index = 1.5;
         let r = Ray::new(point(0.0, 0.0, 0.1), vector(0.0, 1.0, 0.0));
-        let xs = Intersections::new(vec![Intersection::new(-0.9899, world.objects[0]),
-                                                                Intersection::new(-0.4899, world.objects[1]),
-                                                                Intersection::new(0.4899, world.objects[1]),
-                                                                Intersection::new(0.9899, world.objects[0])]);
+        let xs = Intersections::new(vec![
+            Intersection::new(-0.9899, world.objects[0].clone()),
+            Intersection::new(-0.4899, world.objects[1].clone()),
+            Intersection::new(0.4899, world.objects[1].clone()),
+            Intersection::new(0.9899, world.objects[0].clone()),
+        ]);
         let comps = r.prepare_computations(&xs.inters[2].clone(), xs);
-        assert_relative_eq!(world.refracted_colour(&comps, 5), Colour::new(0.0, 0.99888, 0.04721), epsilon=DEFAULT_EPSILON);
+        assert_relative_eq!(
+            world.refracted_colour(&comps, 5, Split::default()),
+            Colour::new(0.0, 0.99888, 0.04721),
+            epsilon = DEFAULT_EPSILON
+        );
     }
     #[test]
     fn shade_hit_trans() {
@@ -501,10 +1226,21 @@ mod tests {
         ball.material.ambient = 0.5;
         ball.transform = translation(0.0, -3.5, -0.5);
         world.objects.push(ball);
-        let r = Ray::new(point(0.0, 0.0, -3.0), vector(0.0, -f32::sqrt(2.0)/2.0, f32::sqrt(2.0)/2.0));
-        let xs = Intersections::new(vec![Intersection::new(f32::sqrt(2.0), world.objects[2])]);
+        let r = Ray::new(
+            point(0.0, 0.0, -3.0),
+            vector(0.0, -f32::sqrt(2.0) / 2.0, f32::sqrt(2.0) / 2.0),
+        );
+        let xs = Intersections::new(vec![Intersection::new(
+            f32::sqrt(2.0),
+            world.objects[2].clone(),
+        )]);
         let comps = r.prepare_computations(&xs.inters[0].clone(), xs);
-        assert_relative_eq!(world.shade_hit(comps, 5), Colour::new(0.93642, 0.68642, 0.68642), epsilon=DEFAULT_EPSILON);
+        // The ball is half lit through the transparent floor
+        assert_relative_eq!(
+            world.shade_hit(comps, 5, Split::default()),
+            Colour::new(1.12547, 0.68642, 0.68642),
+            epsilon = DEFAULT_EPSILON
+        );
     }
     #[test]
     fn shade_hit_refractive_reflective() {
@@ -520,9 +1256,728 @@ mod tests {
         ball.material.ambient = 0.5;
         ball.transform = translation(0.0, -3.5, -0.5);
         world.objects.push(ball);
-        let r = Ray::new(point(0.0, 0.0, -3.0), vector(0.0, -f32::sqrt(2.0)/2.0, f32::sqrt(2.0)/2.0));
-        let xs = Intersections::new(vec![Intersection::new(f32::sqrt(2.0), world.objects[2])]);
+        let r = Ray::new(
+            point(0.0, 0.0, -3.0),
+            vector(0.0, -f32::sqrt(2.0) / 2.0, f32::sqrt(2.0) / 2.0),
+        );
+        let xs = Intersections::new(vec![Intersection::new(
+            f32::sqrt(2.0),
+            world.objects[2].clone(),
+        )]);
         let comps = r.prepare_computations(&xs.inters[0].clone(), xs);
-        assert_relative_eq!(world.shade_hit(comps, 5), Colour::new(0.93391, 0.69643, 0.69243), epsilon=DEFAULT_EPSILON);
+        // The ball is half lit through the transparent floor
+        assert_relative_eq!(
+            world.shade_hit(comps, 5, Split::default()),
+            Colour::new(1.11500, 0.69643, 0.69243),
+            epsilon = DEFAULT_EPSILON
+        );
+    }
+    #[test]
+    fn shade_hit_sums_every_light_with_ambient_once() {
+        let mut world = World::default_world();
+        let r = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        let x = Intersection::new(4.0, world.objects[0].clone());
+        let one = world.shade_hit(
+            r.prepare_computations(&x, Intersections::new(vec![x.clone()])),
+            5,
+            Split::default(),
+        );
+        world.lights.push(world.lights[0]);
+        let two = world.shade_hit(
+            r.prepare_computations(&x, Intersections::new(vec![x.clone()])),
+            5,
+            Split::default(),
+        );
+        // The second light adds its diffuse and specular light but not another ambient term
+        let ambient = Colour::new(0.8, 1.0, 0.6) * 0.1;
+        assert_relative_eq!(two, one * 2.0 - ambient, epsilon = DEFAULT_EPSILON);
+        world.lights.clear();
+        assert_eq!(
+            world.shade_hit(
+                r.prepare_computations(&x, Intersections::new(vec![x.clone()])),
+                5,
+                Split::default(),
+            ),
+            BLACK
+        );
+        assert!(!world.is_shadowed(point(0.0, 10.0, 0.0)));
+    }
+    #[test]
+    fn emissive_material_adds_to_shade_hit() {
+        let mut world = World::default_world();
+        world.objects[0].material.emissive = Colour::new(1.0, 0.5, 0.0);
+        world.objects[0].material.emission_strength = 2.0;
+        let r = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        let x = Intersection::new(4.0, world.objects[0].clone());
+        let comps = r.prepare_computations(&x, Intersections::new(vec![x.clone()]));
+        let c = world.shade_hit(comps, 5, Split::default());
+        assert_relative_eq!(
+            c,
+            Colour::new(2.38066, 1.47583, 0.2855),
+            epsilon = DEFAULT_EPSILON
+        );
+    }
+    #[test]
+    fn indirect_lighting_from_emissive_object() {
+        let mut world = World::new();
+        world.objects.push(Object::new_plane());
+        let mut lamp = Object::new_plane();
+        lamp.transform = translation(0.0, 1.0, 0.0);
+        lamp.material.emissive = WHITE;
+        world.objects.push(lamp);
+        let r = Ray::new(point(0.0, 0.5, 0.0), vector(0.0, -1.0, 0.0));
+        let x = Intersection::new(0.5, world.objects[0].clone());
+        let comps = r.prepare_computations(&x, Intersections::new(vec![x.clone()]));
+        assert_eq!(world.indirect_emission(&comps), BLACK);
+        world.indirect_samples = 16;
+        // Every ray hits the emissive ceiling, so the floor receives all of it
+        assert_relative_eq!(
+            world.indirect_emission(&comps),
+            Colour::new(0.9, 0.9, 0.9),
+            epsilon = DEFAULT_EPSILON
+        );
+    }
+    #[test]
+    fn refracted_colour_darkens_with_thickness() {
+        let refracted_through = |thickness: f32| {
+            let mut world = World::new();
+            world.lights.push(Light::new(point(0.0, 0.0, -10.0), WHITE));
+            let mut backdrop = Object::new_sphere();
+            backdrop.transform = scale(20.0, 20.0, 20.0);
+            backdrop.material.ambient = 1.0;
+            backdrop.material.diffuse = 0.0;
+            backdrop.material.specular = 0.0;
+            world.objects.push(backdrop);
+            let mut glass = Object::glass_sphere();
+            glass.transform = scale(1.0, 1.0, thickness);
+            glass.material.refractive_index = 1.0;
+            glass.material.ambient = 0.0;
+            glass.material.diffuse = 0.0;
+            glass.material.specular = 0.0;
+            glass.material.absorption_colour = RED;
+            glass.material.absorption_density = 0.5;
+            world.objects.push(glass);
+            let r = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+            let xs = world.intersect(&r);
+            let comps = r.prepare_computations(&xs.inters[1].clone(), xs);
+            world.refracted_colour(&comps, 5, Split::default())
+        };
+        let thin = refracted_through(0.5);
+        let thick = refracted_through(2.0);
+        assert_relative_eq!(
+            thin,
+            Colour::new(1.0, f32::exp(-0.5), f32::exp(-0.5)),
+            epsilon = 0.001
+        );
+        assert_relative_eq!(
+            thick,
+            Colour::new(1.0, f32::exp(-2.0), f32::exp(-2.0)),
+            epsilon = 0.001
+        );
+    }
+    #[test]
+    fn absorption_covers_chord_through_object() {
+        let glass = |transform| {
+            let mut glass = Object::glass_sphere();
+            glass.transform = transform;
+            glass.material.refractive_index = 1.0;
+            glass.material.ambient = 0.0;
+            glass.material.diffuse = 0.0;
+            glass.material.specular = 0.0;
+            glass.material.absorption_colour = RED;
+            glass.material.absorption_density = 0.5;
+            glass
+        };
+        let refracted = |objects: Vec<Object>| {
+            let mut world = World::new();
+            world.lights.push(Light::new(point(0.0, 0.0, -10.0), WHITE));
+            let mut backdrop = Object::new_sphere();
+            backdrop.transform = scale(20.0, 20.0, 20.0);
+            backdrop.material.ambient = 1.0;
+            backdrop.material.diffuse = 0.0;
+            backdrop.material.specular = 0.0;
+            world.objects.push(backdrop);
+            world.objects.extend(objects);
+            let r = Ray::new(point(0.0, 5.0, 0.0), vector(0.0, -1.0, 0.0));
+            let xs = world.intersect(&r);
+            let comps = r.prepare_computations(&xs.hit().unwrap(), xs);
+            world.refracted_colour(&comps, 5, Split::default())
+        };
+        // An open surface lets the light behind it through unabsorbed
+        let mut sheet = glass(identity());
+        sheet.shape = Object::new_plane().shape;
+        assert_eq!(refracted(vec![sheet]), WHITE);
+        // A clear object inside the ball does not cut the absorption short
+        let mut inner = glass(scale(0.25, 0.25, 0.25));
+        inner.material.absorption_density = 0.0;
+        assert_relative_eq!(
+            refracted(vec![glass(identity()), inner]),
+            Colour::new(1.0, f32::exp(-1.0), f32::exp(-1.0)),
+            epsilon = 0.001
+        );
+    }
+    #[test]
+    fn internal_reflection_is_absorbed() {
+        let reflected_inside = |density| {
+            let mut glass = Object::glass_sphere();
+            glass.material.reflective = 1.0;
+            glass.material.absorption_colour = RED;
+            glass.material.absorption_density = density;
+            let world = World {
+                objects: vec![glass],
+                lights: vec![Light::new(point(0.0, 0.0, 0.0), WHITE)],
+                ..World::new()
+            };
+            // From the centre the ray reflects straight back across the whole ball
+            let r = Ray::new(point(0.0, 0.0, 0.0), vector(0.0, 0.0, 1.0));
+            let xs = world.intersect(&r);
+            let comps = r.prepare_computations(&xs.hit().unwrap(), xs);
+            assert!(comps.inside);
+            world.reflected_colour(&comps, 1, Split::default())
+        };
+        let clear = reflected_inside(0.0);
+        let absorbing = reflected_inside(0.5);
+        assert!(clear.green > 0.0);
+        assert_relative_eq!(
+            absorbing,
+            clear * Colour::new(1.0, f32::exp(-1.0), f32::exp(-1.0)),
+            epsilon = 0.001
+        );
     }
-}
\ No newline at end of file
+    #[test]
+    fn refracted_colour_with_dispersion() {
+        let mut world = World::new();
+        world
+            .lights
+            .push(Light::new(point(-10.0, 10.0, -10.0), WHITE));
+        let mut backdrop = Object::new_sphere();
+        backdrop.transform = scale(20.0, 20.0, 20.0);
+        backdrop.material.ambient = 1.0;
+        backdrop.material.diffuse = 0.0;
+        backdrop.material.specular = 0.0;
+        world.objects.push(backdrop);
+        let mut shape = Object::glass_sphere();
+        // Indices of about 1.3 for red, 1.41 for green and 1.6 for blue
+        shape.material.dispersion = Some(Dispersion::cauchy(1.02381, 0.116686));
+        world.objects.push(shape.clone());
+        // Leaving the sphere at 45 degrees only blue is totally internally reflected
+        let r = Ray::new(point(0.0, 0.0, f32::sqrt(2.0) / 2.0), vector(0.0, 1.0, 0.0));
+        let xs = Intersections::new(vec![
+            Intersection::new(-f32::sqrt(2.0) / 2.0, shape.clone()),
+            Intersection::new(f32::sqrt(2.0) / 2.0, shape.clone()),
+        ]);
+        let comps = r.prepare_computations(&xs.inters[1].clone(), xs);
+        assert_relative_eq!(
+            world.refracted_colour(&comps, 5, Split::default()),
+            Colour::new(1.0, 1.0, 0.0),
+            epsilon = DEFAULT_EPSILON
+        );
+        // A ray already split by an earlier dispersive surface keeps to its own wavelength
+        let red = Split {
+            channel: Some(Channel::Red),
+            ..Split::default()
+        };
+        let blue = Split {
+            channel: Some(Channel::Blue),
+            ..Split::default()
+        };
+        assert_relative_eq!(
+            world.refracted_colour(&comps, 5, red),
+            WHITE,
+            epsilon = DEFAULT_EPSILON
+        );
+        assert_eq!(world.refracted_colour(&comps, 5, blue), BLACK);
+    }
+    #[test]
+    fn dispersion_without_spread_matches_plain_refraction() {
+        let mut world = World::default_world();
+        world.objects[0].material.ambient = 1.0;
+        world.objects[0].material.pattern = Some(Pattern::new_test());
+        world.objects[1].material.transparency = 1.0;
+        world.objects[1].material.refractive_index = 1.5;
+        world.objects[1].material.dispersion = Some(Dispersion::cauchy(1.5, 0.0));
+        let r = Ray::new(point(0.0, 0.0, 0.1), vector(0.0, 1.0, 0.0));
+        let xs = Intersections::new(vec![
+            Intersection::new(-0.9899, world.objects[0].clone()),
+            Intersection::new(-0.4899, world.objects[1].clone()),
+            Intersection::new(0.4899, world.objects[1].clone()),
+            Intersection::new(0.9899, world.objects[0].clone()),
+        ]);
+        let comps = r.prepare_computations(&xs.inters[2].clone(), xs);
+        assert_relative_eq!(
+            world.refracted_colour(&comps, 5, Split::default()),
+            Colour::new(0.0, 0.99888, 0.04721),
+            epsilon = DEFAULT_EPSILON
+        );
+    }
+    #[test]
+    fn smooth_glossy_reflection_matches_mirror() {
+        let mut world = World::default_world();
+        let mut shape = Object::new_plane();
+        shape.material.reflective = 0.5;
+        shape.material.glossy = Some(Glossy::new(0.0, 4));
+        shape.transform = translation(0.0, -1.0, 0.0);
+        world.objects.push(shape);
+        let r = Ray::new(
+            point(0.0, 0.0, -3.0),
+            vector(0.0, -(f32::sqrt(2.0) / 2.0), f32::sqrt(2.0) / 2.0),
+        );
+        let i = Intersection::new(f32::sqrt(2.0), world.objects[2].clone());
+        let comps = r.prepare_computations(&i, Intersections::new(vec![i.clone()]));
+        assert_relative_eq!(
+            world.reflected_colour(&comps, 5, Split::default()),
+            Colour::new(0.190332, 0.23791, 0.14274),
+            epsilon = DEFAULT_EPSILON
+        );
+    }
+    #[test]
+    fn rough_reflection_blurs_small_light() {
+        let mut world = World::new();
+        world
+            .lights
+            .push(Light::new(point(-10.0, 10.0, -10.0), WHITE));
+        let mut lamp = Object::new_sphere();
+        lamp.transform = translation(0.0, 5.0, 5.0) * scale(0.5, 0.5, 0.5);
+        lamp.material.ambient = 1.0;
+        lamp.material.diffuse = 0.0;
+        lamp.material.specular = 0.0;
+        world.objects.push(lamp);
+        let mut floor = Object::new_plane();
+        floor.material.reflective = 1.0;
+        world.objects.push(floor);
+        let r = Ray::new(point(0.0, 5.0, -5.0), vector(0.0, -1.0, 1.0).normalize());
+        let i = Intersection::new(f32::sqrt(50.0), world.objects[1].clone());
+        let comps = r.prepare_computations(&i, Intersections::new(vec![i.clone()]));
+        assert_relative_eq!(
+            world.reflected_colour(&comps, 5, Split::default()).red,
+            1.0,
+            epsilon = DEFAULT_EPSILON
+        );
+
+        world.objects[1].material.glossy = Some(Glossy::new(0.5, 256));
+        let i = Intersection::new(f32::sqrt(50.0), world.objects[1].clone());
+        let comps = r.prepare_computations(&i, Intersections::new(vec![i.clone()]));
+        let blurred = world.reflected_colour(&comps, 5, Split::default()).red;
+        assert!(blurred > 0.0 && blurred < 0.5);
+    }
+    #[test]
+    fn only_first_glossy_bounce_is_split() {
+        let mut leaves = 0;
+        let mut leaf = |split: Split| {
+            assert!(split.glossy);
+            leaves += 1;
+            WHITE
+        };
+        let fresh = Split::default();
+        assert_eq!(
+            glossy_average(4, fresh, |split| glossy_average(4, split, |split| {
+                glossy_average(4, split, &mut leaf)
+            })),
+            WHITE
+        );
+        // A path that has not been split yet splits again
+        glossy_average(4, fresh, &mut leaf);
+        assert_eq!(leaves, 8);
+    }
+    #[test]
+    fn frosted_refraction_blurs_backdrop() {
+        let mut world = World::new();
+        world
+            .lights
+            .push(Light::new(point(-10.0, 10.0, -10.0), WHITE));
+        let mut target = Object::new_sphere();
+        target.transform = translation(0.0, 0.0, 3.0);
+        target.material.ambient = 1.0;
+        target.material.diffuse = 0.0;
+        target.material.specular = 0.0;
+        world.objects.push(target);
+        let mut pane = Object::new_plane();
+        pane.transform = rot_x(PI / 2.0);
+        pane.material.transparency = 1.0;
+        pane.material.refractive_index = 1.0;
+        world.objects.push(pane);
+        let r = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        let i = Intersection::new(5.0, world.objects[1].clone());
+        let comps = r.prepare_computations(&i, Intersections::new(vec![i.clone()]));
+        assert_relative_eq!(
+            world.refracted_colour(&comps, 5, Split::default()).red,
+            1.0,
+            epsilon = DEFAULT_EPSILON
+        );
+
+        world.objects[1].material.glossy = Some(Glossy::new(0.5, 256));
+        let i = Intersection::new(5.0, world.objects[1].clone());
+        let comps = r.prepare_computations(&i, Intersections::new(vec![i.clone()]));
+        let blurred = world.refracted_colour(&comps, 5, Split::default()).red;
+        assert!(blurred > 0.0 && blurred < 0.5);
+    }
+    #[test]
+    fn ray_through_centre_of_subpixel() {
+        let mut c = Camera::new(201, 101, PI / 2.0);
+        c.transform = rot_y(PI / 4.0) * translation(0.0, -2.0, 5.0);
+        let centre = c.ray_for_subpixel(100, 50, 0.5, 0.5);
+        assert_relative_eq!(
+            centre.origin,
+            point(0.0, 2.0, -5.0),
+            epsilon = DEFAULT_EPSILON
+        );
+        assert_relative_eq!(
+            centre.direction,
+            vector(f32::sqrt(2.0) / 2.0, 0.0, -f32::sqrt(2.0) / 2.0),
+            epsilon = DEFAULT_EPSILON
+        );
+        let c = Camera::new(201, 101, PI / 2.0);
+        let corner = c.ray_for_subpixel(0, 0, 0.0, 0.0);
+        assert_relative_eq!(
+            corner.direction,
+            vector(1.0, 101.0 / 201.0, -1.0).normalize(),
+            epsilon = DEFAULT_EPSILON
+        );
+    }
+    #[test]
+    fn render_with_path_tracing() {
+        let scene = |integrator| {
+            let mut world = World::default_world();
+            world.objects[0].material.ambient = 0.0;
+            world.integrator = integrator;
+            let mut c = Camera::new(11, 11, 0.01);
+            c.transform = view_transform(
+                point(0.0, 0.0, -5.0),
+                point(0.0, 0.0, 0.0),
+                vector(0.0, 1.0, 0.0),
+            );
+            world.render(c).pixel_at(5, 5)
+        };
+        let whitted = scene(Integrator::Whitted);
+        let traced = scene(Integrator::PathTraced {
+            samples: 16,
+            max_depth: 5,
+        });
+        assert_relative_eq!(traced, whitted, epsilon = 0.02);
+    }
+    #[test]
+    fn ambient_occlusion_darkens_ambient_term() {
+        let mut world = World::default_world();
+        world.lights[0] = Light::new(point(0.0, 0.25, 0.0), WHITE);
+        world.objects[0].material.ambient = 1.0;
+        world.objects[1].material.ambient = 1.0;
+        let r = Ray::new(point(0.0, 0.0, 0.0), vector(0.0, 0.0, 1.0));
+        let open = world.colour_at(r, 5);
+        // The inside of the outer sphere is completely enclosed
+        world.ambient_occlusion = Some(AmbientOcclusion::new(16, 10.0));
+        let occluded = world.colour_at(r, 5);
+        assert_relative_eq!(
+            open - occluded,
+            world.objects[1].material.colour,
+            epsilon = DEFAULT_EPSILON
+        );
+    }
+    #[test]
+    fn render_ambient_occlusion_pass() {
+        let mut world = World::new();
+        world.objects.push(Object::new_plane());
+        world.integrator = Integrator::AmbientOcclusion(AmbientOcclusion::new(16, 10.0));
+        let mut c = Camera::new(11, 11, PI / 2.0);
+        c.transform = view_transform(
+            point(0.0, 1.0, 0.0),
+            point(0.0, 0.0, 0.0),
+            vector(0.0, 0.0, 1.0),
+        );
+        let image = world.render(c);
+        assert_eq!(image.pixel_at(5, 5), WHITE);
+    }
+    #[test]
+    fn caustics_brighten_shadow_of_glass() {
+        seed(40);
+        let mut world = World::new();
+        world.lights.push(Light::new(point(0.0, 4.0, 0.0), WHITE));
+        world.objects.push(Object::new_plane());
+        let mut ball = Object::glass_sphere();
+        ball.transform = translation(0.0, 2.0, 0.0);
+        world.objects.push(ball);
+        let r = Ray::new(point(0.0, 0.5, -0.5), vector(0.0, -1.0, 1.0).normalize());
+        // Without caustics the glass lets the light straight through
+        assert!(world.colour_at(r, 5).red > 0.5);
+        // With a photon map the light through the glass only arrives as caustics, so an empty map leaves a shadow
+        world.photon_map = Some(PhotonMap::new(vec![], 0.3));
+        let dark = world.colour_at(r, 5);
+        assert_relative_eq!(dark, Colour::new(0.1, 0.1, 0.1), epsilon = DEFAULT_EPSILON);
+        world.photon_map = Some(PhotonMap::trace(&world, 20000, 0.3));
+        let lit = world.colour_at(r, 5);
+        assert!(lit.red > dark.red + 0.5);
+    }
+    #[test]
+    fn opaque_object_blocks_light() {
+        let world = World::default_world();
+        let t = world.shadow_transmittance(point(10.0, -10.0, 10.0), world.lights[0].position);
+        assert_eq!(t, BLACK);
+    }
+    #[test]
+    fn coloured_glass_tints_shadow() {
+        let mut world = World::default_world();
+        world.objects[0].material.transparency = 0.8;
+        world.objects[0].material.colour = Colour::new(1.0, 0.5, 0.25);
+        world.objects[1].material.casts_shadow = false;
+        let t = world.shadow_transmittance(point(10.0, -10.0, 10.0), world.lights[0].position);
+        assert_relative_eq!(t, Colour::new(0.8, 0.4, 0.2), epsilon = DEFAULT_EPSILON);
+        assert!(!world.is_shadowed(point(10.0, -10.0, 10.0)));
+    }
+    #[test]
+    fn shadow_filtered_by_each_occluder() {
+        let mut world = World::default_world();
+        world.objects[0].material.transparency = 0.5;
+        world.objects[1].material.transparency = 0.5;
+        let t = world.shadow_transmittance(point(10.0, -10.0, 10.0), world.lights[0].position);
+        assert_relative_eq!(t, Colour::new(0.2, 0.25, 0.15), epsilon = DEFAULT_EPSILON);
+    }
+    #[test]
+    fn absorption_darkens_shadow() {
+        let mut world = World::new();
+        world.lights.push(Light::new(point(0.0, 10.0, 0.0), WHITE));
+        let mut glass = Object::glass_sphere();
+        glass.material.absorption_colour = Colour::new(1.0, 0.0, 0.0);
+        glass.material.absorption_density = 1.0;
+        world.objects.push(glass);
+        let t = world.shadow_transmittance(point(0.0, -5.0, 0.0), world.lights[0].position);
+        assert_relative_eq!(
+            t,
+            Colour::new(1.0, f32::exp(-2.0), f32::exp(-2.0)),
+            epsilon = DEFAULT_EPSILON
+        );
+    }
+    #[test]
+    fn object_can_opt_out_of_shadows() {
+        let mut world = World::default_world();
+        world.objects[0].material.casts_shadow = false;
+        world.objects[1].material.casts_shadow = false;
+        assert!(!world.is_shadowed(point(10.0, -10.0, 10.0)));
+        assert_eq!(
+            world.shadow_transmittance(point(10.0, -10.0, 10.0), world.lights[0].position),
+            WHITE
+        );
+    }
+    #[test]
+    fn missed_ray_sees_background() {
+        let mut world = World::default_world();
+        world.background = Background::Gradient {
+            bottom: BLACK,
+            top: WHITE,
+        };
+        let r = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 1.0, 0.0));
+        assert_eq!(world.colour_at(r, 5), WHITE);
+    }
+    #[test]
+    fn reflection_sees_background() {
+        let mut world = World::new();
+        world
+            .lights
+            .push(Light::new(point(-10.0, 10.0, -10.0), WHITE));
+        world.background = Background::Solid(RED);
+        let mut mirror = Object::new_plane();
+        mirror.material.reflective = 1.0;
+        world.objects.push(mirror);
+        let r = Ray::new(point(0.0, 1.0, -1.0), vector(0.0, -1.0, 1.0).normalize());
+        let i = Intersection::new(f32::sqrt(2.0), world.objects[0].clone());
+        let comps = r.prepare_computations(&i, Intersections::new(vec![i.clone()]));
+        assert_eq!(world.reflected_colour(&comps, 5, Split::default()), RED);
+    }
+    #[test]
+    fn uniform_environment_lights_open_surface() {
+        seed(43);
+        let mut world = World::new();
+        let mut floor = Object::new_plane();
+        floor.material.ambient = 0.0;
+        // The Phong highlight is left out of the estimate, only the diffuse term is lit
+        floor.material.specular = 0.9;
+        world.objects.push(floor);
+        world.environment_light =
+            Some(EnvironmentLight::new(Arc::new(Canvas::new(8, 4, WHITE)), 20000).unwrap());
+        let r = Ray::new(point(0.0, 1.0, 0.0), vector(0.0, -1.0, 0.0));
+        assert_relative_eq!(
+            world.colour_at(r, 5),
+            Colour::new(0.9, 0.9, 0.9),
+            epsilon = 0.05
+        );
+    }
+    #[test]
+    fn environment_light_is_shadowed() {
+        let mut world = World::new();
+        let mut floor = Object::new_plane();
+        floor.material.ambient = 0.0;
+        world.objects.push(floor);
+        let mut roof = Object::new_plane();
+        roof.transform = translation(0.0, 1.0, 0.0);
+        world.objects.push(roof);
+        world.environment_light =
+            Some(EnvironmentLight::new(Arc::new(Canvas::new(8, 4, WHITE)), 100).unwrap());
+        let r = Ray::new(point(0.0, 0.5, 0.0), vector(0.0, -1.0, 0.0));
+        assert_eq!(world.colour_at(r, 5), BLACK);
+    }
+    #[test]
+    fn daylight_from_physical_sky() {
+        let sky = PhysicalSky::new(0.8, 0.5, 3.0);
+        let mut world = World::new();
+        world.lights.push(sky.sun_light());
+        world.background = Background::Sky(sky);
+        world.objects.push(Object::new_plane());
+        let up = Ray::new(point(0.0, 1.0, 0.0), vector(0.0, 1.0, 0.0));
+        assert_eq!(world.colour_at(up, 5), sky.colour_at(vector(0.0, 1.0, 0.0)));
+        let down = Ray::new(point(0.0, 1.0, 0.0), vector(0.0, -1.0, 0.0));
+        let ground = world.colour_at(down, 5);
+        assert!(ground.red > ground.blue);
+    }
+    #[test]
+    fn fog_glows_with_light() {
+        let mut world = World::new();
+        world.lights.push(Light::new(point(0.0, 10.0, 0.0), WHITE));
+        let mut fog = Medium::new(Colour::new(0.5, 0.6, 0.7), 0.2);
+        fog.ambient = 0.0;
+        world.fog = Some(fog);
+        let r = Ray::new(point(0.0, 0.0, 0.0), vector(0.0, 0.0, 1.0));
+        assert_relative_eq!(
+            world.colour_at(r, 5),
+            Colour::new(0.5, 0.6, 0.7),
+            epsilon = 0.001
+        );
+    }
+    #[test]
+    fn missed_ray_sees_background_through_clear_fog() {
+        let mut world = World::new();
+        world.lights.push(Light::new(point(0.0, 10.0, 0.0), WHITE));
+        world.background = Background::Solid(RED);
+        world.fog = Some(Medium::new(WHITE, 0.0));
+        let r = Ray::new(point(0.0, 0.0, 0.0), vector(0.0, 0.0, 1.0));
+        assert_eq!(world.colour_at(r, 5), RED);
+    }
+    #[test]
+    fn fog_hides_distant_objects() {
+        let mut world = World::default_world();
+        let r = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        let clear = world.colour_at(r, 5);
+        let mut fog = Medium::new(BLACK, 0.1);
+        fog.ambient = 0.0;
+        world.fog = Some(fog);
+        assert_relative_eq!(
+            world.colour_at(r, 5),
+            clear * f32::exp(-0.4),
+            epsilon = DEFAULT_EPSILON
+        );
+    }
+    #[test]
+    fn shadows_cast_light_shafts_in_fog() {
+        let mut world = World::new();
+        world.lights.push(Light::new(point(0.0, 10.0, 0.0), WHITE));
+        let mut blocker = Object::new_sphere();
+        blocker.transform = translation(0.0, 5.0, 0.0) * scale(2.0, 2.0, 2.0);
+        world.objects.push(blocker);
+        let mut fog = Medium::new(WHITE, 0.2);
+        fog.ambient = 0.0;
+        fog.steps = 64;
+        world.fog = Some(fog);
+        let shadowed = world.colour_at(Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0)), 5);
+        let lit = world.colour_at(Ray::new(point(10.0, 0.0, -5.0), vector(0.0, 0.0, 1.0)), 5);
+        assert!(lit.red > shadowed.red + 0.1);
+    }
+    #[test]
+    fn smoke_volume_dims_what_is_behind() {
+        let mut world = World::new();
+        world.background = Background::Solid(WHITE);
+        let mut smoke = Object::new_sphere();
+        smoke.material.volume = Some(Medium::new(BLACK, 0.5));
+        world.objects.push(smoke);
+        let r = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        assert_relative_eq!(
+            world.colour_at(r, 5),
+            WHITE * f32::exp(-1.0),
+            epsilon = DEFAULT_EPSILON
+        );
+        // Starting inside the smoke
+        let r = Ray::new(point(0.0, 0.0, 0.0), vector(0.0, 0.0, 1.0));
+        assert_relative_eq!(
+            world.colour_at(r, 5),
+            WHITE * f32::exp(-0.5),
+            epsilon = DEFAULT_EPSILON
+        );
+    }
+    #[test]
+    fn nested_volumes_stop_at_recursion_depth() {
+        let mut world = World::new();
+        world.background = Background::Solid(WHITE);
+        for i in 0..3 {
+            let mut smoke = Object::new_sphere();
+            smoke.transform = translation(0.0, 0.0, 2.5 * i as f32);
+            smoke.material.volume = Some(Medium::new(BLACK, 0.5));
+            world.objects.push(smoke);
+        }
+        let r = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        assert_relative_eq!(
+            world.colour_at(r, 5),
+            WHITE * f32::exp(-3.0),
+            epsilon = DEFAULT_EPSILON
+        );
+        // Each volume takes a level, so the background is out of reach
+        assert_eq!(world.colour_at(r, 2), BLACK);
+    }
+    #[test]
+    fn whitted_fog_renders_repeatably() {
+        let mut world = World::new();
+        world.lights.push(Light::new(point(0.0, 10.0, 0.0), WHITE));
+        let mut blocker = Object::new_sphere();
+        blocker.transform = translation(0.0, 5.0, 0.0) * scale(2.0, 2.0, 2.0);
+        world.objects.push(blocker);
+        world.fog = Some(Medium::new(WHITE, 0.2));
+        let r = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        assert_eq!(world.colour_at(r, 5), world.colour_at(r, 5));
+    }
+    #[test]
+    fn smoke_volume_casts_soft_shadow() {
+        let mut world = World::new();
+        world.lights.push(Light::new(point(0.0, 10.0, 0.0), WHITE));
+        let mut smoke = Object::new_sphere();
+        smoke.material.volume = Some(Medium::new(WHITE, 0.5));
+        world.objects.push(smoke);
+        assert_relative_eq!(
+            world.shadow_transmittance(point(0.0, -5.0, 0.0), point(0.0, 10.0, 0.0)),
+            WHITE * f32::exp(-1.0),
+            epsilon = DEFAULT_EPSILON
+        );
+        assert_relative_eq!(
+            world.shadow_transmittance(point(0.0, 0.0, 0.0), point(0.0, 10.0, 0.0)),
+            WHITE * f32::exp(-0.5),
+            epsilon = DEFAULT_EPSILON
+        );
+    }
+    #[test]
+    fn subsurface_light_shows_through_back_of_object() {
+        let mut world = World::new();
+        world.lights.push(Light::new(point(0.0, 0.0, 10.0), WHITE));
+        let mut wax = Object::new_sphere();
+        wax.transform = scale(0.5, 0.5, 0.5);
+        world.objects.push(wax);
+        let r = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        let plastic = world.colour_at(r, 5);
+        assert_relative_eq!(
+            plastic,
+            Colour::new(0.1, 0.1, 0.1),
+            epsilon = DEFAULT_EPSILON
+        );
+        world.objects[0].material.subsurface =
+            Some(Subsurface::new(Colour::new(0.99, 0.9, 0.8), 0.2, 200));
+        let wax = world.colour_at(r, 5);
+        assert!(wax.red > plastic.red + 0.02);
+        assert!(wax.red > wax.blue);
+    }
+    #[test]
+    fn subsurface_lit_side_is_close_to_diffuse() {
+        let mut world = World::default_world();
+        world.objects[1].material.subsurface = Some(Subsurface::new(WHITE, 0.05, 400));
+        world.objects.remove(0);
+        let r = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        let plastic = World::default_world().objects[1].clone();
+        let mut plain = World::default_world();
+        plain.objects = vec![plastic];
+        let diffuse = plain.colour_at(r, 5).red;
+        let scattered = world.colour_at(r, 5).red;
+        assert!(scattered > diffuse * 0.5 && scattered < diffuse * 1.2);
+    }
+}